@@ -0,0 +1,340 @@
+use crate::validate::MAX_ORDINAL;
+use crate::{Export, Import, ModuleDefinitionFile, Section};
+use alloc::string::{String, ToString};
+use core::fmt::{Display, Formatter};
+
+/// Fluent builder for [`ModuleDefinitionFile`].
+///
+/// Values are checked as they are set, and the first problem is returned by [`build`](Self::build).
+/// Item attributes such as [`ordinal`](Self::ordinal) and [`read`](Self::read) apply to the most recently added
+/// export or section.
+///
+/// Names are stored the same way as the parser would store them, without quotes.
+/// The writer adds quotes to names that need them.
+///
+/// ```rust
+/// # use msvc_def::ModuleDefinitionFile;
+/// let file = ModuleDefinitionFile::library("foo")
+///     .base(0x10000000)
+///     .stack(1 << 20, None)
+///     .export("bar")
+///     .ordinal(3)
+///     .data()
+///     .export("baz")
+///     .internal_name("inner_baz")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(
+///     file.write_to_buffer().unwrap(),
+///     "LIBRARY foo BASE=0x10000000\nSTACKSIZE 0x100000\nEXPORTS\n    bar @3 DATA\n    baz=inner_baz\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ModuleDefinitionFileBuilder {
+    file: ModuleDefinitionFile,
+    error: Option<BuildError>,
+}
+
+impl ModuleDefinitionFileBuilder {
+    /// Create new [`ModuleDefinitionFileBuilder`] for a file without a `NAME` or `LIBRARY` statement.
+    pub fn new() -> Self {
+        Self {
+            file: ModuleDefinitionFile::default(),
+            error: None,
+        }
+    }
+
+    fn with_name(name: &str, is_library: bool) -> Self {
+        let mut builder = Self::new();
+        if let Some(name) = builder.check_name(name) {
+            builder.file.name = Some(name.to_string());
+            builder.file.is_library = Some(is_library);
+        }
+        builder
+    }
+
+    /// The `BASE` argument of the `NAME` or `LIBRARY` statement.
+    pub fn base(mut self, address: u64) -> Self {
+        if self.file.name.is_none() {
+            self.set_error(BuildError::BaseWithoutName);
+        }
+        self.file.base_address = Some(address);
+        self
+    }
+
+    /// The `HEAPSIZE` statement.
+    pub const fn heap(mut self, reserve: u64, commit: Option<u64>) -> Self {
+        self.file.heap_reserve = Some(reserve);
+        self.file.heap_commit = commit;
+        self
+    }
+
+    /// The `STACKSIZE` statement.
+    pub const fn stack(mut self, reserve: u64, commit: Option<u64>) -> Self {
+        self.file.stack_reserve = Some(reserve);
+        self.file.stack_commit = commit;
+        self
+    }
+
+    /// The `STUB` statement.
+    pub fn stub(mut self, file_name: &str) -> Self {
+        if let Some(stub) = self.check_name(file_name) {
+            self.file.stub = Some(stub.to_string());
+        }
+        self
+    }
+
+    /// The `DESCRIPTION` statement.
+    pub fn description(mut self, description: &str) -> Self {
+        if description.contains(['\r', '\n']) {
+            self.set_error(BuildError::InvalidName(description.to_string()));
+        } else {
+            self.file.description = Some(description.to_string());
+        }
+        self
+    }
+
+    /// The `VERSION` statement.
+    ///
+    /// Both numbers must fit in 16 bits.
+    pub fn version(mut self, major: u64, minor: u64) -> Self {
+        for n in [major, minor] {
+            if n > u64::from(u16::MAX) {
+                self.set_error(BuildError::VersionTooLarge(n));
+                return self;
+            }
+        }
+
+        self.file.major_version = Some(major as u16);
+        self.file.minor_version = Some(minor as u16);
+        self
+    }
+
+    /// Add a section to the `SECTIONS` statement without any attributes.
+    pub fn section(mut self, name: &str) -> Self {
+        if let Some(name) = self.check_item_name(name) {
+            self.file
+                .sections
+                .push(Section::new(name, false, false, false, false));
+        }
+        self
+    }
+
+    /// Add `READ` to the most recent section.
+    pub fn read(self) -> Self {
+        self.with_section(|s| s.read = true)
+    }
+
+    /// Add `WRITE` to the most recent section.
+    pub fn write(self) -> Self {
+        self.with_section(|s| s.write = true)
+    }
+
+    /// Add `EXECUTE` to the most recent section.
+    pub fn execute(self) -> Self {
+        self.with_section(|s| s.execute = true)
+    }
+
+    /// Add `SHARED` to the most recent section.
+    pub fn shared(self) -> Self {
+        self.with_section(|s| s.shared = true)
+    }
+
+    /// Add an export to the `EXPORTS` statement without any attributes.
+    pub fn export(mut self, name: &str) -> Self {
+        if let Some(name) = self.check_item_name(name) {
+            self.file
+                .exports
+                .push(Export::new(name, None, None, false, false, false));
+        }
+        self
+    }
+
+    /// Set the internal name of the most recent export.
+    pub fn internal_name(mut self, internal_name: &str) -> Self {
+        let Some(internal_name) = self.check_item_name(internal_name) else {
+            return self;
+        };
+        self.with_export(|e| e.internal_name = Some(internal_name))
+    }
+
+    /// Set the ordinal of the most recent export.
+    ///
+    /// Must be between 1 and 65535.
+    pub fn ordinal(mut self, ordinal: u64) -> Self {
+        if ordinal == 0 || ordinal > MAX_ORDINAL {
+            self.set_error(BuildError::OrdinalOutOfRange(ordinal));
+            return self;
+        }
+        self.with_export(|e| e.ordinal = Some(ordinal))
+    }
+
+    /// Add `NONAME` to the most recent export.
+    ///
+    /// The export must have an [`ordinal`](Self::ordinal) when [`build`](Self::build) is called.
+    pub fn noname(self) -> Self {
+        self.with_export(|e| e.noname = true)
+    }
+
+    /// Add `PRIVATE` to the most recent export.
+    pub fn private(self) -> Self {
+        self.with_export(|e| e.private = true)
+    }
+
+    /// Add `DATA` to the most recent export.
+    pub fn data(self) -> Self {
+        self.with_export(|e| e.data = true)
+    }
+
+    /// Add an import of `entry_name` from `module` to the `IMPORTS` statement.
+    pub fn import(mut self, module: &str, entry_name: &str) -> Self {
+        let module = self.check_name(module);
+        let entry_name = self.check_name(entry_name);
+        if let (Some(module), Some(entry_name)) = (module, entry_name) {
+            self.file.imports.push(Import::new(
+                None,
+                module.to_string(),
+                Some(entry_name.to_string()),
+                None,
+            ));
+        }
+        self
+    }
+
+    /// Add an import of `ordinal` from `module` to the `IMPORTS` statement.
+    pub fn import_ordinal(mut self, module: &str, ordinal: u64) -> Self {
+        if ordinal == 0 || ordinal > MAX_ORDINAL {
+            self.set_error(BuildError::OrdinalOutOfRange(ordinal));
+            return self;
+        }
+        if let Some(module) = self.check_name(module) {
+            self.file
+                .imports
+                .push(Import::new(None, module.to_string(), None, Some(ordinal)));
+        }
+        self
+    }
+
+    /// Finish the file.
+    ///
+    /// # Errors
+    ///
+    /// The first problem found while building.
+    pub fn build(self) -> Result<ModuleDefinitionFile, BuildError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        if let Some(e) = self
+            .file
+            .exports
+            .iter()
+            .find(|e| e.noname && e.ordinal.is_none())
+        {
+            return Err(BuildError::NonameWithoutOrdinal(e.name.clone()));
+        }
+
+        Ok(self.file)
+    }
+
+    fn set_error(&mut self, e: BuildError) {
+        if self.error.is_none() {
+            self.error = Some(e);
+        }
+    }
+
+    fn with_section(mut self, f: impl FnOnce(&mut Section)) -> Self {
+        match self.file.sections.last_mut() {
+            Some(s) => f(s),
+            None => self.set_error(BuildError::MissingItem("SECTIONS")),
+        }
+        self
+    }
+
+    fn with_export(mut self, f: impl FnOnce(&mut Export)) -> Self {
+        match self.file.exports.last_mut() {
+            Some(e) => f(e),
+            None => self.set_error(BuildError::MissingItem("EXPORTS")),
+        }
+        self
+    }
+
+    /// Names are stored without quotes since the writer adds them.
+    fn check_name<'a>(&mut self, name: &'a str) -> Option<&'a str> {
+        if name.is_empty() || name.contains(['\r', '\n']) {
+            self.set_error(BuildError::InvalidName(name.to_string()));
+            return None;
+        }
+
+        Some(name)
+    }
+
+    fn check_item_name(&mut self, name: &str) -> Option<String> {
+        self.check_name(name).map(ToString::to_string)
+    }
+}
+
+impl Default for ModuleDefinitionFileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ModuleDefinitionFile {
+    /// Start building a file with a `LIBRARY` statement.
+    pub fn library(name: &str) -> ModuleDefinitionFileBuilder {
+        ModuleDefinitionFileBuilder::with_name(name, true)
+    }
+
+    /// Start building a file with a `NAME` statement.
+    pub fn application(name: &str) -> ModuleDefinitionFileBuilder {
+        ModuleDefinitionFileBuilder::with_name(name, false)
+    }
+
+    /// Start building a file without a `NAME` or `LIBRARY` statement.
+    pub fn builder() -> ModuleDefinitionFileBuilder {
+        ModuleDefinitionFileBuilder::new()
+    }
+}
+
+/// Problem found by [`ModuleDefinitionFileBuilder`].
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum BuildError {
+    /// Name is empty or contains a newline, which can not be written.
+    ///
+    /// Also returned for import names that would need quotes or contain a period.
+    InvalidName(String),
+    /// `BASE` is an argument of the `NAME` and `LIBRARY` statements.
+    BaseWithoutName,
+    /// Version number does not fit in 16 bits.
+    VersionTooLarge(u64),
+    /// Ordinal is 0 or does not fit in 16 bits.
+    OrdinalOutOfRange(u64),
+    /// `NONAME` without an ordinal.
+    NonameWithoutOrdinal(String),
+    /// Item attribute set before adding an item to the statement.
+    MissingItem(&'static str),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BuildError::InvalidName(a) => write!(f, "invalid name '{a}'"),
+            BuildError::BaseWithoutName => {
+                write!(f, "'BASE' requires a 'NAME' or 'LIBRARY' statement")
+            }
+            BuildError::VersionTooLarge(a) => write!(f, "version number '{a}' too large"),
+            BuildError::OrdinalOutOfRange(a) => {
+                write!(f, "ordinal '{a}' is not between 1 and {MAX_ORDINAL}")
+            }
+            BuildError::NonameWithoutOrdinal(a) => {
+                write!(f, "export '{a}' is 'NONAME' without an ordinal")
+            }
+            BuildError::MissingItem(a) => write!(f, "attribute set before adding item to '{a}'"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
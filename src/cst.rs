@@ -0,0 +1,548 @@
+use crate::lexer::{Lexer, TokenKind};
+use crate::options::ParseOptions;
+use crate::parse_ref::{needs_quotes, RESERVED_WORDS};
+use crate::token_iterator::keyword;
+use crate::write::write_name;
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+
+/// Kind of [`SyntaxToken`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum SyntaxKind {
+    /// Spaces and tabs, including indentation and byte order marks.
+    Whitespace,
+    /// Either `\n` or `\r\n`.
+    Newline,
+    /// Comment starting with `;` until the end of the line, not including the newline.
+    Comment,
+    /// Keyword, name, number or the period of a version.
+    Word,
+    /// String enclosed in double quotes, including the quotes.
+    QuotedString,
+    /// `=`
+    Equals,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+}
+
+impl SyntaxKind {
+    /// Returns [`true`] for tokens that do not affect the meaning of the file.
+    pub const fn is_trivia(&self) -> bool {
+        matches!(self, Self::Whitespace | Self::Newline | Self::Comment)
+    }
+}
+
+/// Single token in a [`SyntaxTree`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SyntaxToken<'a> {
+    /// Kind of token.
+    pub kind: SyntaxKind,
+    /// Text of the token exactly as it will be written.
+    pub text: Cow<'a, str>,
+    /// Byte span in the original source.
+    ///
+    /// Is [`None`] for tokens that have been added or changed after parsing.
+    pub span: Option<Range<usize>>,
+}
+
+impl<'a> SyntaxToken<'a> {
+    /// Create new [`SyntaxToken`] that is not part of the original source.
+    pub fn new(kind: SyntaxKind, text: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+            span: None,
+        }
+    }
+}
+
+/// Position of an export inside of a [`SyntaxTree`].
+///
+/// All fields are indices into [`SyntaxTree::tokens`],
+/// and are only valid until the tree is modified.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ExportNode {
+    /// All tokens from the name to the last attribute, including trivia in between.
+    pub tokens: Range<usize>,
+    /// Public name of the export.
+    pub name: usize,
+    /// Internal name of the export, after the `=`.
+    pub internal_name: Option<usize>,
+    /// Ordinal including the `@`.
+    pub ordinal: Option<usize>,
+    /// `NONAME` keyword.
+    pub noname: Option<usize>,
+    /// `PRIVATE` keyword.
+    pub private: Option<usize>,
+    /// `DATA` keyword.
+    pub data: Option<usize>,
+    /// Name after the `EXPORTAS` keyword.
+    pub export_as: Option<usize>,
+}
+
+/// Lossless concrete syntax tree that keeps comments, whitespace and layout.
+///
+/// Writing the tree without modifications produces the original source byte for byte,
+/// and modifications only change the tokens they concern.
+///
+/// ```rust
+/// # use msvc_def::SyntaxTree;
+/// let mut tree = SyntaxTree::new("EXPORTS\n    ; basic functions\n    deflate @1 ; comment\n");
+/// tree.set_export_ordinal(0, Some(2));
+/// assert_eq!(tree.to_string(), "EXPORTS\n    ; basic functions\n    deflate @2 ; comment\n");
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SyntaxTree<'a> {
+    tokens: Vec<SyntaxToken<'a>>,
+    case_insensitive: bool,
+}
+
+impl<'a> SyntaxTree<'a> {
+    /// Create a new tree from a `str`.
+    ///
+    /// Tokenization can not fail, so invalid files will still produce a tree.
+    pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, ParseOptions::new())
+    }
+
+    /// Create a new tree from a `str` with custom [`ParseOptions`].
+    ///
+    /// With [`ParseOptions::case_insensitive`] keywords such as `exports` and `Data` are recognized by
+    /// [`exports`](Self::exports), while keeping their original spelling.
+    /// The [`dialect`](ParseOptions::dialect) is not taken into account.
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Self {
+            tokens: lex(source),
+            case_insensitive: options.is_case_insensitive(),
+        }
+    }
+
+    /// All tokens in the tree, including trivia.
+    pub fn tokens(&self) -> &[SyntaxToken<'a>] {
+        &self.tokens
+    }
+
+    /// Replace the text of a single token.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn replace_token(&mut self, index: usize, text: impl Into<Cow<'a, str>>) {
+        let token = &mut self.tokens[index];
+        token.text = text.into();
+        token.span = None;
+    }
+
+    /// Insert tokens before `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the amount of tokens.
+    pub fn insert_tokens(
+        &mut self,
+        index: usize,
+        tokens: impl IntoIterator<Item = SyntaxToken<'a>>,
+    ) {
+        let tail = self.tokens.split_off(index);
+        self.tokens.extend(tokens);
+        self.tokens.extend(tail);
+    }
+
+    /// Remove a range of tokens.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn remove_tokens(&mut self, range: Range<usize>) {
+        self.tokens.drain(range);
+    }
+
+    /// Positions of all exports in all `EXPORTS` statements, in the order they appear.
+    pub fn exports(&self) -> Vec<ExportNode> {
+        let mut exports = Vec::new();
+
+        let mut in_exports = false;
+        let mut i = self.next_significant(0);
+        while let Some(index) = i {
+            let text = keyword(&self.tokens[index].text, self.case_insensitive);
+
+            if !in_exports || is_keyword(text) {
+                in_exports = text == "EXPORTS";
+                i = self.next_significant(index + 1);
+                continue;
+            }
+
+            let mut node = ExportNode {
+                tokens: index..index + 1,
+                name: index,
+                internal_name: None,
+                ordinal: None,
+                noname: None,
+                private: None,
+                data: None,
+                export_as: None,
+            };
+
+            i = self.next_significant(index + 1);
+            while let Some(index) = i {
+                let token = &self.tokens[index];
+                match keyword(&token.text, self.case_insensitive) {
+                    "=" if token.kind == SyntaxKind::Equals => {
+                        let Some(internal) = self.next_significant(index + 1) else {
+                            break;
+                        };
+                        node.internal_name = Some(internal);
+                        node.tokens.end = internal + 1;
+                        i = self.next_significant(internal + 1);
+                        continue;
+                    }
+                    "NONAME" => node.noname = Some(index),
+                    "PRIVATE" => node.private = Some(index),
+                    "DATA" => node.data = Some(index),
+                    "EXPORTAS" => {
+                        let Some(export_as) = self.next_significant(index + 1) else {
+                            break;
+                        };
+                        node.export_as = Some(export_as);
+                        node.tokens.end = export_as + 1;
+                        i = self.next_significant(export_as + 1);
+                        continue;
+                    }
+                    ord if ord.starts_with('@') => node.ordinal = Some(index),
+                    _ => break,
+                }
+
+                node.tokens.end = index + 1;
+                i = self.next_significant(index + 1);
+            }
+
+            exports.push(node);
+        }
+
+        exports
+    }
+
+    /// Change the public name of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// The name is quoted if necessary, with embedded quotes doubled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_name(&mut self, export: usize, name: impl Into<Cow<'a, str>>) {
+        let node = &self.exports()[export];
+        self.tokens[node.name] = name_token(name.into());
+    }
+
+    /// Change or remove the internal name of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// The name is quoted like in [`set_export_name`](Self::set_export_name).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_internal_name(
+        &mut self,
+        export: usize,
+        internal_name: Option<impl Into<Cow<'a, str>>>,
+    ) {
+        let node = self.exports().swap_remove(export);
+
+        match (node.internal_name, internal_name) {
+            (Some(index), Some(internal_name)) => {
+                self.tokens[index] = name_token(internal_name.into());
+            }
+            (None, Some(internal_name)) => self.insert_tokens(
+                node.name + 1,
+                [
+                    SyntaxToken::new(SyntaxKind::Equals, "="),
+                    name_token(internal_name.into()),
+                ],
+            ),
+            (Some(index), None) => self.remove_tokens(node.name + 1..index + 1),
+            (None, None) => {}
+        }
+    }
+
+    /// Change, add or remove the ordinal of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_ordinal(&mut self, export: usize, ordinal: Option<u64>) {
+        let node = self.exports().swap_remove(export);
+
+        match (node.ordinal, ordinal) {
+            (Some(index), Some(ordinal)) => self.replace_token(index, alloc::format!("@{ordinal}")),
+            (None, Some(ordinal)) => self.insert_tokens(
+                node.internal_name.unwrap_or(node.name) + 1,
+                [
+                    SyntaxToken::new(SyntaxKind::Whitespace, " "),
+                    SyntaxToken::new(SyntaxKind::Word, alloc::format!("@{ordinal}")),
+                ],
+            ),
+            (Some(index), None) => self.remove_with_leading_whitespace(index),
+            (None, None) => {}
+        }
+    }
+
+    /// Add or remove the `NONAME` keyword of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_noname(&mut self, export: usize, noname: bool) {
+        let node = self.exports().swap_remove(export);
+        self.set_keyword(&node, node.noname, "NONAME", noname);
+    }
+
+    /// Add or remove the `PRIVATE` keyword of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_private(&mut self, export: usize, private: bool) {
+        let node = self.exports().swap_remove(export);
+        self.set_keyword(&node, node.private, "PRIVATE", private);
+    }
+
+    /// Add or remove the `DATA` keyword of the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn set_export_data(&mut self, export: usize, data: bool) {
+        let node = self.exports().swap_remove(export);
+        self.set_keyword(&node, node.data, "DATA", data);
+    }
+
+    /// Remove the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// If the export is the only thing on its line the entire line is removed,
+    /// including indentation and trailing comments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is out of bounds.
+    pub fn remove_export(&mut self, export: usize) {
+        let node = self.exports().swap_remove(export);
+
+        let line_start = self.tokens[..node.tokens.start]
+            .iter()
+            .rposition(|a| a.kind == SyntaxKind::Newline)
+            .map_or(0, |a| a + 1);
+        let line_end = self.tokens[node.tokens.end..]
+            .iter()
+            .position(|a| a.kind == SyntaxKind::Newline)
+            .map_or(self.tokens.len(), |a| node.tokens.end + a);
+
+        let alone = self.tokens[line_start..node.tokens.start]
+            .iter()
+            .chain(&self.tokens[node.tokens.end..line_end])
+            .all(|a| a.kind.is_trivia());
+
+        if alone {
+            let end = if line_end < self.tokens.len() {
+                line_end + 1
+            } else {
+                line_end
+            };
+            self.remove_tokens(line_start..end);
+        } else {
+            self.remove_tokens(node.tokens.clone());
+            if node.tokens.start > 0
+                && self.tokens[node.tokens.start - 1].kind == SyntaxKind::Whitespace
+            {
+                self.remove_tokens(node.tokens.start - 1..node.tokens.start);
+            }
+        }
+    }
+
+    /// Insert a new export on its own line before the export at position `export` in [`exports`](Self::exports).
+    ///
+    /// `export` may be equal to the amount of exports in order to insert after the last export.
+    /// The indentation of the surrounding exports is reused,
+    /// and an `EXPORTS` statement is added at the end of the file if there are no exports.
+    ///
+    /// `text` is everything following the indentation, for example `myfunc=inner @3 DATA`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `export` is greater than the amount of exports.
+    pub fn insert_export(&mut self, export: usize, text: &str) {
+        let newline = self
+            .tokens
+            .iter()
+            .find(|a| a.kind == SyntaxKind::Newline)
+            .map_or(Cow::Borrowed("\n"), |a| a.text.clone());
+
+        let exports = self.exports();
+        assert!(export <= exports.len(), "export index out of bounds");
+
+        let mut tokens = Vec::new();
+
+        let index = if let Some(node) = exports.get(export) {
+            let line_start = self.line_start(node.name);
+            tokens.extend(self.tokens[line_start..node.name].iter().cloned());
+            tokens.extend(lex_owned(text));
+            tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline));
+
+            line_start
+        } else if let Some(node) = exports.last() {
+            let line_start = self.line_start(node.name);
+            let line_end = self.tokens[node.tokens.end..]
+                .iter()
+                .position(|a| a.kind == SyntaxKind::Newline)
+                .map(|a| node.tokens.end + a);
+
+            if line_end.is_none() {
+                tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline.clone()));
+            }
+            tokens.extend(self.tokens[line_start..node.name].iter().cloned());
+            tokens.extend(lex_owned(text));
+            if line_end.is_some() {
+                tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline));
+            }
+
+            line_end.map_or(self.tokens.len(), |a| a + 1)
+        } else {
+            if self
+                .tokens
+                .last()
+                .map_or(false, |a| a.kind != SyntaxKind::Newline)
+            {
+                tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline.clone()));
+            }
+            tokens.push(SyntaxToken::new(SyntaxKind::Word, "EXPORTS"));
+            tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline.clone()));
+            tokens.push(SyntaxToken::new(SyntaxKind::Whitespace, "    "));
+            tokens.extend(lex_owned(text));
+            tokens.push(SyntaxToken::new(SyntaxKind::Newline, newline));
+
+            self.tokens.len()
+        };
+
+        self.insert_tokens(index, tokens);
+    }
+
+    fn set_keyword(
+        &mut self,
+        node: &ExportNode,
+        index: Option<usize>,
+        keyword: &'static str,
+        present: bool,
+    ) {
+        match (index, present) {
+            (None, true) => self.insert_tokens(
+                node.tokens.end,
+                [
+                    SyntaxToken::new(SyntaxKind::Whitespace, " "),
+                    SyntaxToken::new(SyntaxKind::Word, keyword),
+                ],
+            ),
+            (Some(index), false) => self.remove_with_leading_whitespace(index),
+            _ => {}
+        }
+    }
+
+    fn remove_with_leading_whitespace(&mut self, index: usize) {
+        if index > 0 && self.tokens[index - 1].kind == SyntaxKind::Whitespace {
+            self.remove_tokens(index - 1..index + 1);
+        } else {
+            self.remove_tokens(index..index + 1);
+        }
+    }
+
+    fn line_start(&self, index: usize) -> usize {
+        self.tokens[..index]
+            .iter()
+            .rposition(|a| a.kind == SyntaxKind::Newline)
+            .map_or(0, |a| a + 1)
+    }
+
+    fn next_significant(&self, start: usize) -> Option<usize> {
+        self.tokens[start..]
+            .iter()
+            .position(|a| !a.kind.is_trivia())
+            .map(|a| a + start)
+    }
+}
+
+/// Quotes `name` if it would not be read as a single name otherwise.
+fn name_token(name: Cow<'_, str>) -> SyntaxToken<'_> {
+    if !needs_quotes(&name) {
+        return SyntaxToken::new(SyntaxKind::Word, name);
+    }
+
+    let mut text = String::new();
+    write_name(&mut text, &name, true, true).unwrap();
+    SyntaxToken::new(SyntaxKind::QuotedString, text)
+}
+
+impl Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for token in &self.tokens {
+            f.write_str(&token.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_keyword(s: &str) -> bool {
+    RESERVED_WORDS.contains(&s) && !matches!(s, "NONAME" | "PRIVATE" | "DATA")
+}
+
+fn lex_owned<'a>(s: &str) -> Vec<SyntaxToken<'a>> {
+    lex(s)
+        .into_iter()
+        .map(|a| SyntaxToken::new(a.kind, a.text.to_string()))
+        .collect()
+}
+
+/// Tokens of the [`Lexer`] with the whitespace between them.
+fn lex(s: &str) -> Vec<SyntaxToken<'_>> {
+    let mut tokens = Vec::new();
+
+    let mut offset = 0;
+    let mut push = |kind, range: Range<usize>| {
+        tokens.push(SyntaxToken {
+            kind,
+            text: Cow::Borrowed(&s[range.clone()]),
+            span: Some(range),
+        });
+    };
+
+    for token in Lexer::new(s) {
+        if offset < token.span.start {
+            push(SyntaxKind::Whitespace, offset..token.span.start);
+        }
+
+        let kind = match token.kind {
+            TokenKind::Newline => SyntaxKind::Newline,
+            TokenKind::Comment => SyntaxKind::Comment,
+            TokenKind::QuotedString => SyntaxKind::QuotedString,
+            TokenKind::Equals => SyntaxKind::Equals,
+            TokenKind::Colon => SyntaxKind::Colon,
+            TokenKind::Comma => SyntaxKind::Comma,
+            TokenKind::Keyword
+            | TokenKind::Identifier
+            | TokenKind::Number
+            | TokenKind::Ordinal
+            | TokenKind::Period => SyntaxKind::Word,
+        };
+        push(kind, token.span.start..token.span.end);
+        offset = token.span.end;
+    }
+
+    if offset < s.len() {
+        push(SyntaxKind::Whitespace, offset..s.len());
+    }
+
+    tokens
+}
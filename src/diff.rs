@@ -0,0 +1,447 @@
+use crate::{Export, ModuleDefinitionFile, Section};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// Difference between two versions of a file, found by [`diff`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Change<'a> {
+    /// Is [`true`] if modules built against the old file may stop working with the new one.
+    pub breaking: bool,
+    /// Kind of change.
+    pub kind: ChangeKind<'a>,
+}
+
+impl<'a> Change<'a> {
+    /// Create new [`Change`] that is breaking according to [`ChangeKind::is_breaking`].
+    pub const fn new(kind: ChangeKind<'a>) -> Self {
+        Self {
+            breaking: kind.is_breaking(),
+            kind,
+        }
+    }
+}
+
+impl Display for Change<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let breaking = if self.breaking {
+            "breaking"
+        } else {
+            "non-breaking"
+        };
+        write!(f, "{breaking}: ")?;
+
+        match self.kind {
+            ChangeKind::NameChanged { old, new } => {
+                write!(f, "module name changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::VersionChanged { old, new } => {
+                write!(
+                    f,
+                    "version changed from {}.{} to {}.{}",
+                    old.0, old.1, new.0, new.1
+                )
+            }
+            ChangeKind::ExportAdded(e) => write!(f, "export '{}' was added", e.name),
+            ChangeKind::ExportRemoved(e) => write!(f, "export '{}' was removed", e.name),
+            ChangeKind::InternalNameChanged { name, old, new } => {
+                write!(f, "internal name of export '{name}' changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::OrdinalChanged { name, old, new } => {
+                write!(f, "ordinal of export '{name}' changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::NonameChanged { name, noname } => {
+                let change = if noname { "became" } else { "is no longer" };
+                write!(f, "export '{name}' {change} 'NONAME'")
+            }
+            ChangeKind::PrivateChanged { name, private } => {
+                let change = if private { "became" } else { "is no longer" };
+                write!(f, "export '{name}' {change} 'PRIVATE'")
+            }
+            ChangeKind::DataChanged { name, data } => {
+                let (from, to) = if data {
+                    ("code", "data")
+                } else {
+                    ("data", "code")
+                };
+                write!(f, "export '{name}' changed from {from} to {to}")
+            }
+            ChangeKind::ArgumentBytesChanged { name, old, new } => {
+                write!(f, "argument bytes of export '{name}' changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::ImportAliasChanged { name, old, new } => {
+                write!(f, "import alias of export '{name}' changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::ConstantChanged { name, constant } => {
+                let change = if constant { "became" } else { "is no longer" };
+                write!(f, "export '{name}' {change} 'CONSTANT'")
+            }
+            ChangeKind::ExportAsChanged { name, old, new } => {
+                write!(f, "'EXPORTAS' name of export '{name}' changed from ")?;
+                write_optional(f, old)?;
+                write!(f, " to ")?;
+                write_optional(f, new)
+            }
+            ChangeKind::SectionAdded(s) => write!(f, "section '{}' was added", s.name),
+            ChangeKind::SectionRemoved(s) => write!(f, "section '{}' was removed", s.name),
+            ChangeKind::SectionAttributesChanged { old, new } => {
+                write!(f, "attributes of section '{}' changed", new.name)?;
+                write!(f, " from ")?;
+                write_attributes(f, old)?;
+                write!(f, " to ")?;
+                write_attributes(f, new)
+            }
+        }
+    }
+}
+
+fn write_optional(f: &mut Formatter<'_>, value: Option<impl Display>) -> core::fmt::Result {
+    match value {
+        Some(value) => write!(f, "'{value}'"),
+        None => write!(f, "nothing"),
+    }
+}
+
+fn write_attributes(f: &mut Formatter<'_>, s: &Section) -> core::fmt::Result {
+    let attributes = [
+        (s.read, "READ"),
+        (s.write, "WRITE"),
+        (s.execute, "EXECUTE"),
+        (s.shared, "SHARED"),
+    ];
+
+    write!(f, "'")?;
+    let mut first = true;
+    for (_, attribute) in attributes.iter().filter(|(enabled, _)| *enabled) {
+        if !first {
+            write!(f, " ")?;
+        }
+        write!(f, "{attribute}")?;
+        first = false;
+    }
+    write!(f, "'")
+}
+
+/// Kind of change.
+///
+/// Exports and sections are matched by name.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ChangeKind<'a> {
+    /// The name in the `NAME` or `LIBRARY` statement changed.
+    ///
+    /// Breaking since import libraries refer to the module by name.
+    NameChanged {
+        /// Old name.
+        old: Option<&'a str>,
+        /// New name.
+        new: Option<&'a str>,
+    },
+    /// The `VERSION` statement changed, with missing numbers treated as 0.
+    ///
+    /// Breaking if the major version changed.
+    VersionChanged {
+        /// Old major and minor version.
+        old: (u16, u16),
+        /// New major and minor version.
+        new: (u16, u16),
+    },
+    /// Export only in the new file.
+    ///
+    /// Never breaking.
+    ExportAdded(&'a Export),
+    /// Export only in the old file.
+    ///
+    /// Always breaking.
+    ExportRemoved(&'a Export),
+    /// [`Export::internal_name`] changed.
+    ///
+    /// Never breaking since the exported name stays the same.
+    InternalNameChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// Old internal name.
+        old: Option<&'a str>,
+        /// New internal name.
+        new: Option<&'a str>,
+    },
+    /// [`Export::ordinal`] changed.
+    ///
+    /// Breaking unless an ordinal was added to an export that did not have one,
+    /// since modules may import by ordinal.
+    OrdinalChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// Old ordinal.
+        old: Option<u64>,
+        /// New ordinal.
+        new: Option<u64>,
+    },
+    /// [`Export::noname`] changed.
+    ///
+    /// Breaking if the export became `NONAME` since it can no longer be imported by name.
+    NonameChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// New value.
+        noname: bool,
+    },
+    /// [`Export::private`] changed.
+    ///
+    /// Breaking if the export became `PRIVATE` since it is no longer in the import library.
+    PrivateChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// New value.
+        private: bool,
+    },
+    /// [`Export::data`] changed.
+    ///
+    /// Always breaking since data and code are imported differently.
+    DataChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// New value.
+        data: bool,
+    },
+    /// [`Export::argument_bytes`] changed.
+    ///
+    /// Always breaking since the stdcall decoration of the imported symbol changes.
+    ArgumentBytesChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// Old amount of bytes.
+        old: Option<u64>,
+        /// New amount of bytes.
+        new: Option<u64>,
+    },
+    /// [`Export::import_alias`] changed.
+    ///
+    /// Always breaking since a different name is imported from the module.
+    ImportAliasChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// Old import alias.
+        old: Option<&'a str>,
+        /// New import alias.
+        new: Option<&'a str>,
+    },
+    /// [`Export::constant`] changed.
+    ///
+    /// Always breaking since constants are imported without `__imp_`.
+    ConstantChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// New value.
+        constant: bool,
+    },
+    /// [`Export::export_as`] changed.
+    ///
+    /// Always breaking since a different name is imported from the module.
+    ExportAsChanged {
+        /// Name of the export.
+        name: &'a str,
+        /// Old name in the module.
+        old: Option<&'a str>,
+        /// New name in the module.
+        new: Option<&'a str>,
+    },
+    /// Section only in the new file.
+    ///
+    /// Never breaking.
+    SectionAdded(&'a Section),
+    /// Section only in the old file.
+    ///
+    /// Always breaking since the section goes back to the default attributes.
+    SectionRemoved(&'a Section),
+    /// Attributes of a section changed.
+    ///
+    /// Breaking if any attribute was removed.
+    SectionAttributesChanged {
+        /// Old section.
+        old: &'a Section,
+        /// New section.
+        new: &'a Section,
+    },
+}
+
+impl ChangeKind<'_> {
+    /// Is [`true`] if modules built against the old file may stop working with the new one.
+    pub const fn is_breaking(&self) -> bool {
+        match *self {
+            ChangeKind::NameChanged { .. } => true,
+            ChangeKind::VersionChanged { old, new } => old.0 != new.0,
+            ChangeKind::ExportAdded(_) => false,
+            ChangeKind::ExportRemoved(_) => true,
+            ChangeKind::InternalNameChanged { .. } => false,
+            ChangeKind::OrdinalChanged { old, .. } => old.is_some(),
+            ChangeKind::NonameChanged { noname, .. } => noname,
+            ChangeKind::PrivateChanged { private, .. } => private,
+            ChangeKind::DataChanged { .. } => true,
+            ChangeKind::ArgumentBytesChanged { .. } => true,
+            ChangeKind::ImportAliasChanged { .. } => true,
+            ChangeKind::ConstantChanged { .. } => true,
+            ChangeKind::ExportAsChanged { .. } => true,
+            ChangeKind::SectionAdded(_) => false,
+            ChangeKind::SectionRemoved(_) => true,
+            ChangeKind::SectionAttributesChanged { old, new } => {
+                (old.read && !new.read)
+                    || (old.write && !new.write)
+                    || (old.execute && !new.execute)
+                    || (old.shared && !new.shared)
+            }
+        }
+    }
+}
+
+/// Compare two versions of a file.
+///
+/// Changes to the module name and version come first, followed by changed and removed exports
+/// in the order of `old`, added exports in the order of `new`, and the same for sections.
+///
+/// ```rust
+/// # use msvc_def::ChangeKind;
+/// let old = msvc_def::parse("EXPORTS\n    a @1\n    b @2\n").unwrap();
+/// let new = msvc_def::parse("EXPORTS\n    a @1\n    c @3\n").unwrap();
+///
+/// let changes = msvc_def::diff(&old, &new);
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].kind, ChangeKind::ExportRemoved(&old.exports[1]));
+/// assert!(changes[0].breaking);
+/// assert_eq!(changes[1].kind, ChangeKind::ExportAdded(&new.exports[1]));
+/// assert!(!changes[1].breaking);
+/// ```
+pub fn diff<'a>(old: &'a ModuleDefinitionFile, new: &'a ModuleDefinitionFile) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    let mut push = |kind| changes.push(Change::new(kind));
+
+    if old.name != new.name {
+        push(ChangeKind::NameChanged {
+            old: old.name.as_deref(),
+            new: new.name.as_deref(),
+        });
+    }
+
+    let old_version = (
+        old.major_version.unwrap_or(0),
+        old.minor_version.unwrap_or(0),
+    );
+    let new_version = (
+        new.major_version.unwrap_or(0),
+        new.minor_version.unwrap_or(0),
+    );
+    if old_version != new_version {
+        push(ChangeKind::VersionChanged {
+            old: old_version,
+            new: new_version,
+        });
+    }
+
+    for o in &old.exports {
+        let Some(n) = new.exports.iter().find(|n| n.name == o.name) else {
+            push(ChangeKind::ExportRemoved(o));
+            continue;
+        };
+        let name = o.name.as_str();
+
+        if o.internal_name != n.internal_name {
+            push(ChangeKind::InternalNameChanged {
+                name,
+                old: o.internal_name.as_deref(),
+                new: n.internal_name.as_deref(),
+            });
+        }
+
+        if o.ordinal != n.ordinal {
+            push(ChangeKind::OrdinalChanged {
+                name,
+                old: o.ordinal,
+                new: n.ordinal,
+            });
+        }
+
+        if o.noname != n.noname {
+            push(ChangeKind::NonameChanged {
+                name,
+                noname: n.noname,
+            });
+        }
+
+        if o.private != n.private {
+            push(ChangeKind::PrivateChanged {
+                name,
+                private: n.private,
+            });
+        }
+
+        if o.data != n.data {
+            push(ChangeKind::DataChanged { name, data: n.data });
+        }
+
+        if o.argument_bytes != n.argument_bytes {
+            push(ChangeKind::ArgumentBytesChanged {
+                name,
+                old: o.argument_bytes,
+                new: n.argument_bytes,
+            });
+        }
+
+        if o.import_alias != n.import_alias {
+            push(ChangeKind::ImportAliasChanged {
+                name,
+                old: o.import_alias.as_deref(),
+                new: n.import_alias.as_deref(),
+            });
+        }
+
+        if o.constant != n.constant {
+            push(ChangeKind::ConstantChanged {
+                name,
+                constant: n.constant,
+            });
+        }
+
+        if o.export_as != n.export_as {
+            push(ChangeKind::ExportAsChanged {
+                name,
+                old: o.export_as.as_deref(),
+                new: n.export_as.as_deref(),
+            });
+        }
+    }
+
+    for n in &new.exports {
+        if !old.exports.iter().any(|o| o.name == n.name) {
+            push(ChangeKind::ExportAdded(n));
+        }
+    }
+
+    for o in &old.sections {
+        match new.sections.iter().find(|n| n.name == o.name) {
+            None => push(ChangeKind::SectionRemoved(o)),
+            Some(n) if o != n => push(ChangeKind::SectionAttributesChanged { old: o, new: n }),
+            Some(_) => {}
+        }
+    }
+
+    for n in &new.sections {
+        if !old.sections.iter().any(|o| o.name == n.name) {
+            push(ChangeKind::SectionAdded(n));
+        }
+    }
+
+    changes
+}
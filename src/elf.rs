@@ -0,0 +1,151 @@
+use crate::{ExportTarget, ModuleDefinitionFile};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+
+const INDENT: &str = "    ";
+
+/// Problem found by [`ModuleDefinitionFile::to_version_script`] and [`ModuleDefinitionFile::to_dynamic_list`].
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ElfError {
+    /// Export name contains a `"`.
+    ///
+    /// Linkers read quoted names verbatim without escape sequences,
+    /// so the name can not be written.
+    InvalidName(String),
+}
+
+impl Display for ElfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ElfError::InvalidName(a) => {
+                write!(f, "export '{a}' contains a '\"' which can not be quoted")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ElfError {}
+
+impl ModuleDefinitionFile {
+    /// Create a GNU `ld` version script for `--version-script`, which makes the exports the only
+    /// global symbols of a shared object.
+    ///
+    /// Every export with a name is listed under `global:` and everything else is `local: *;`.
+    /// The version node is the uppercase [`name`](Self::name) followed by `VERSION`,
+    /// such as `FOO_1.2`, and is anonymous without a `VERSION`.
+    ///
+    /// `NONAME` exports and exports forwarded to another module are not listed
+    /// since ELF has no equivalent.
+    ///
+    /// ```rust
+    /// let file = msvc_def::parse("LIBRARY foo\nVERSION 1.2\nEXPORTS\n    func\n    var DATA\n").unwrap();
+    /// assert_eq!(
+    ///     file.to_version_script().unwrap(),
+    ///     "FOO_1.2 {\n    global:\n        func;\n        var;\n    local:\n        *;\n};\n",
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If an export name can not be written, as described by [`ElfError`].
+    pub fn to_version_script(&self) -> Result<String, ElfError> {
+        let mut s = String::new();
+
+        if let Some(major) = self.major_version {
+            let minor = self.minor_version.unwrap_or_default();
+            let name = self.name.as_deref().map_or_else(
+                || String::from("VERS"),
+                |name| {
+                    name.chars()
+                        .map(|c| match c {
+                            'A'..='Z' | '0'..='9' | '_' => c,
+                            'a'..='z' => c.to_ascii_uppercase(),
+                            _ => '_',
+                        })
+                        .collect()
+                },
+            );
+            s.push_str(&format!("{name}_{major}.{minor} "));
+        }
+
+        s.push_str("{\n");
+        // `ld` rejects an empty `global:`
+        let mut symbols = self.elf_symbols().peekable();
+        if symbols.peek().is_some() {
+            s.push_str(INDENT);
+            s.push_str("global:\n");
+        }
+        for symbol in symbols {
+            write_symbol(&mut s, symbol, 2)?;
+        }
+        s.push_str(INDENT);
+        s.push_str("local:\n");
+        write_symbol(&mut s, "*", 2)?;
+        s.push_str("};\n");
+
+        Ok(s)
+    }
+
+    /// Create a GNU `ld` dynamic list for `--dynamic-list`, which adds the exports to the dynamic
+    /// symbol table without hiding other symbols.
+    ///
+    /// The same exports as [`to_version_script`](Self::to_version_script) are listed.
+    ///
+    /// ```rust
+    /// let file = msvc_def::parse("LIBRARY foo\nEXPORTS\n    func\n    hidden @1 NONAME\n").unwrap();
+    /// assert_eq!(file.to_dynamic_list().unwrap(), "{\n    func;\n};\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If an export name can not be written, as described by [`ElfError`].
+    pub fn to_dynamic_list(&self) -> Result<String, ElfError> {
+        let mut s = String::from("{\n");
+        for symbol in self.elf_symbols() {
+            write_symbol(&mut s, symbol, 1)?;
+        }
+        s.push_str("};\n");
+
+        Ok(s)
+    }
+
+    fn elf_symbols(&self) -> impl Iterator<Item = &str> {
+        self.exports
+            .iter()
+            .filter(|e| !e.noname && matches!(e.target(), ExportTarget::Symbol(_)))
+            .map(|e| e.name.as_str())
+    }
+}
+
+/// Names with other characters than those of C identifiers are quoted,
+/// since `ld` treats them as glob patterns otherwise.
+///
+/// GNU `ld`, `gold` and `lld` end quoted names at the next `"` and have no escape sequences,
+/// so a `\` is written as is.
+fn write_symbol(s: &mut String, symbol: &str, indentation: usize) -> Result<(), ElfError> {
+    if symbol.contains('"') {
+        return Err(ElfError::InvalidName(symbol.into()));
+    }
+
+    for _ in 0..indentation {
+        s.push_str(INDENT);
+    }
+
+    let plain = symbol == "*"
+        || symbol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.'));
+    if plain {
+        s.push_str(symbol);
+    } else {
+        s.push('"');
+        s.push_str(symbol);
+        s.push('"');
+    }
+
+    s.push_str(";\n");
+
+    Ok(())
+}
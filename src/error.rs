@@ -1,18 +1,201 @@
+#[cfg(feature = "alloc")]
+use alloc::string::ToString;
 use core::fmt::{Debug, Display, Formatter};
 
-/// The possible errors during parsing along with an index into the string for where the problem began.
+/// Byte range into the string being parsed.
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Span {
+    /// Offset of the first byte.
+    pub start: usize,
+    /// Offset of the byte after the last byte.
+    ///
+    /// Is equal to [`start`](Self::start) for errors that concern something missing.
+    pub end: usize,
+}
+
+impl Span {
+    /// Create new [`Span`].
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Length in bytes.
+    pub const fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Returns [`true`] if the span has a length of zero.
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Line and column of [`start`](Self::start) in `source`.
+    ///
+    /// `source` must be the same string that was parsed.
+    pub fn line_column(&self, source: &str) -> LineColumn {
+        LineColumn::new(source, self.start)
+    }
+}
+
+/// 1-based line and column.
+///
+/// Columns are counted in characters, not bytes.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct LineColumn {
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number.
+    pub column: usize,
+}
+
+impl LineColumn {
+    fn new(source: &str, offset: usize) -> Self {
+        let before = &source[..floor_char_boundary(source, offset)];
+        let line_start = before.rfind('\n').map_or(0, |a| a + 1);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl Display for LineColumn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+fn floor_char_boundary(s: &str, mut offset: usize) -> usize {
+    if offset >= s.len() {
+        return s.len();
+    }
+
+    while !s.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+/// Render `message` along with the line of `source` that `span` points to and a caret underline.
+#[cfg(feature = "alloc")]
+pub(crate) fn render(
+    source: &str,
+    span: Span,
+    severity: Severity,
+    message: &dyn Display,
+) -> alloc::string::String {
+    let mut s = alloc::string::String::new();
+    // Writing to a String can not fail
+    let _ = render_to(source, span, severity, message, &mut s);
+    s
+}
+
+#[cfg(feature = "alloc")]
+fn render_to(
+    source: &str,
+    span: Span,
+    severity: Severity,
+    message: &dyn Display,
+    f: &mut impl core::fmt::Write,
+) -> core::fmt::Result {
+    let start = floor_char_boundary(source, span.start);
+    let position = LineColumn::new(source, start);
+
+    let line_start = source[..start].rfind('\n').map_or(0, |a| a + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |a| start + a);
+    let line = source[line_start..line_end].trim_end_matches('\r');
+
+    let before = &source[line_start..start];
+    let end = floor_char_boundary(source, span.end.max(start)).min(line_start + line.len());
+    let underline = source[start.min(end)..end].chars().count().max(1);
+
+    let gutter = position.line.to_string();
+    let padding = " ".repeat(gutter.len());
+
+    writeln!(f, "{severity}: {message}")?;
+    writeln!(f, "{padding}--> {position}")?;
+    writeln!(f, "{padding} |")?;
+    writeln!(f, "{gutter} | {line}")?;
+    write!(f, "{padding} | ")?;
+    // Keep tabs so that the caret lines up with the line above
+    for c in before.chars() {
+        f.write_char(if c == '\t' { '\t' } else { ' ' })?;
+    }
+    for _ in 0..underline {
+        f.write_char('^')?;
+    }
+    writeln!(f)
+}
+
+/// The possible errors during parsing along with a span into the string for where the problem is.
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ParseError<'a> {
     /// Kind of error.
     pub kind: ParseErrorKind<'a>,
-    /// Offset into string being parsed.
-    pub offset: usize,
+    /// Span of the problem in the string being parsed.
+    pub span: Span,
 }
 
 impl<'a> ParseError<'a> {
-    /// Create new [`ParseError`].
+    /// Create new [`ParseError`] starting at `offset`.
+    ///
+    /// The span covers the argument of kinds that contain the offending token,
+    /// and is empty for other kinds.
     pub const fn new(kind: ParseErrorKind<'a>, offset: usize) -> Self {
-        Self { kind, offset }
+        let len = match kind {
+            ParseErrorKind::InvalidNumericalArgument(a)
+            | ParseErrorKind::NumberTooLarge(a)
+            | ParseErrorKind::UnknownStatement(a)
+            | ParseErrorKind::UnexpectedToken(a) => a.len(),
+            ParseErrorKind::MissingArgumentFor(_)
+            | ParseErrorKind::MissingDesignatorFor(_)
+            | ParseErrorKind::MissingArgumentAfterCommaFor(_)
+            | ParseErrorKind::MissingModuleFor(_) => 0,
+        };
+
+        Self::with_span(kind, Span::new(offset, offset + len))
+    }
+
+    /// Create new [`ParseError`] with an explicit [`Span`].
+    pub const fn with_span(kind: ParseErrorKind<'a>, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Offset of the start of the problem.
+    pub const fn offset(&self) -> usize {
+        self.span.start
+    }
+
+    /// Line and column of the start of the problem in `source`.
+    ///
+    /// `source` must be the same string that was parsed.
+    pub fn line_column(&self, source: &str) -> LineColumn {
+        self.span.line_column(source)
+    }
+
+    /// Render the error along with the offending line of `source` and a caret underline.
+    ///
+    /// `source` must be the same string that was parsed.
+    ///
+    /// ```text
+    /// error: invalid numerical argument '0b1'
+    ///  --> 2:10
+    ///   |
+    /// 2 | HEAPSIZE 0b1
+    ///   |          ^^^
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn render(&self, source: &str) -> alloc::string::String {
+        self.render_with_severity(source, Severity::Error)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn render_with_severity(&self, source: &str, severity: Severity) -> alloc::string::String {
+        render(source, self.span, severity, self)
     }
 
     pub(crate) const fn missing_arg(keyword: &'static str, offset: usize) -> Self {
@@ -32,6 +215,15 @@ impl<'a> Display for ParseError<'a> {
             ParseErrorKind::MissingArgumentAfterCommaFor(a) => {
                 write!(f, "missing argument after comma for '{a}'")
             }
+            ParseErrorKind::MissingModuleFor(a) => {
+                write!(f, "missing module ('module.entry') for '{a}'")
+            }
+            ParseErrorKind::UnknownStatement(a) => {
+                write!(f, "unknown statement '{a}'")
+            }
+            ParseErrorKind::UnexpectedToken(a) => {
+                write!(f, "unexpected token '{a}'")
+            }
             ParseErrorKind::InvalidNumericalArgument(a) => {
                 write!(f, "invalid numerical argument '{a}'")
             }
@@ -54,8 +246,108 @@ pub enum ParseErrorKind<'a> {
     MissingDesignatorFor(&'static str),
     /// Missing argument for comma separated keyword.
     MissingArgumentAfterCommaFor(&'static str),
+    /// Missing module name separated by a period (`module.entry`).
+    MissingModuleFor(&'static str),
+    /// Statement that is either misspelled or a legacy statement that is not supported.
+    ///
+    /// Only an error when [`ParseOptions::strict`](crate::ParseOptions::strict) is enabled, otherwise a warning.
+    UnknownStatement(&'a str),
+    /// Token that is not part of any statement.
+    ///
+    /// Only an error when [`ParseOptions::strict`](crate::ParseOptions::strict) is enabled, otherwise a warning.
+    UnexpectedToken(&'a str),
     /// Expected number is not parseable as a number.
     InvalidNumericalArgument(&'a str),
     /// Parsed number is outside of allowed limits.
     NumberTooLarge(&'a str),
 }
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Severity {
+    /// The file is invalid.
+    Error,
+    /// The file is valid, but something was ignored.
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        })
+    }
+}
+
+/// Error or warning reported while parsing with
+/// [`parse_ref_recovering`](crate::parse_ref_recovering) or [`parse_recovering`](crate::parse_recovering).
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Diagnostic<'a> {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The problem along with its location.
+    pub error: ParseError<'a>,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// Create new [`Diagnostic`].
+    pub const fn new(severity: Severity, error: ParseError<'a>) -> Self {
+        Self { severity, error }
+    }
+
+    pub(crate) const fn error(error: ParseError<'a>) -> Self {
+        Self::new(Severity::Error, error)
+    }
+
+    pub(crate) const fn warning(error: ParseError<'a>) -> Self {
+        Self::new(Severity::Warning, error)
+    }
+
+    /// Render the diagnostic along with the offending line of `source` and a caret underline.
+    ///
+    /// Same as [`ParseError::render`] except that warnings are labeled as such.
+    #[cfg(feature = "alloc")]
+    pub fn render(&self, source: &str) -> alloc::string::String {
+        self.error.render_with_severity(source, self.severity)
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.error)
+    }
+}
+
+/// Errors when writing a [`ModuleDefinitionFileRef`](crate::ModuleDefinitionFileRef) while propagating errors in items.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum WriteError<'a> {
+    /// Formatting failed, usually because the buffer isn't of sufficient size.
+    Format(core::fmt::Error),
+    /// An item in [`Sections`](crate::Sections), [`Exports`](crate::Exports) or [`Imports`](crate::Imports) could not be parsed.
+    Parse(ParseError<'a>),
+}
+
+impl Display for WriteError<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WriteError::Format(e) => write!(f, "{e}"),
+            WriteError::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WriteError<'_> {}
+
+impl From<core::fmt::Error> for WriteError<'_> {
+    fn from(value: core::fmt::Error) -> Self {
+        Self::Format(value)
+    }
+}
+
+impl<'a> From<ParseError<'a>> for WriteError<'a> {
+    fn from(value: ParseError<'a>) -> Self {
+        Self::Parse(value)
+    }
+}
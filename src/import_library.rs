@@ -0,0 +1,1061 @@
+use crate::parse_ref::split_stdcall_decoration;
+use crate::validate::MAX_ORDINAL;
+use crate::{Export, ModuleDefinitionFile};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+const ARCHIVE_SIGNATURE: &[u8] = b"!<arch>\n";
+const MEMBER_HEADER_SIZE: usize = 60;
+/// Longer names are placed in the `//` member.
+const MAX_MEMBER_NAME: usize = 15;
+
+const FILE_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const IMPORT_DIRECTORY_SIZE: usize = 20;
+const IMPORT_HEADER_SIZE: usize = 20;
+const SYMBOL_SIZE: usize = 18;
+
+const IMAGE_FILE_32BIT_MACHINE: u16 = 0x0100;
+const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+const IMAGE_SCN_ALIGN_2BYTES: u32 = 0x0020_0000;
+const IMAGE_SCN_ALIGN_4BYTES: u32 = 0x0030_0000;
+const IMAGE_SCN_ALIGN_8BYTES: u32 = 0x0040_0000;
+const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+const IMAGE_SYM_CLASS_SECTION: u8 = 104;
+
+const NULL_IMPORT_DESCRIPTOR: &str = "__NULL_IMPORT_DESCRIPTOR";
+
+const IMPORT_CODE: u16 = 0;
+const IMPORT_DATA: u16 = 1;
+const IMPORT_CONST: u16 = 2;
+
+const IMPORT_ORDINAL: u16 = 0;
+const IMPORT_NAME: u16 = 1;
+const IMPORT_NAME_NOPREFIX: u16 = 2;
+const IMPORT_NAME_UNDECORATE: u16 = 3;
+const IMPORT_NAME_EXPORTAS: u16 = 4;
+
+/// Target machine of an import library.
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Machine {
+    /// `IMAGE_FILE_MACHINE_I386`
+    ///
+    /// Symbols are prefixed with an underscore unless they are already decorated.
+    X86,
+    /// `IMAGE_FILE_MACHINE_AMD64`
+    #[default]
+    X64,
+    /// `IMAGE_FILE_MACHINE_ARM64`
+    Arm64,
+    /// `IMAGE_FILE_MACHINE_ARM64EC`
+    ///
+    /// Functions are also available through their mangled `#name` symbol.
+    Arm64Ec,
+}
+
+impl Machine {
+    /// Value of the `Machine` field in COFF headers.
+    pub const fn value(&self) -> u16 {
+        match self {
+            Machine::X86 => 0x014C,
+            Machine::X64 => 0x8664,
+            Machine::Arm64 => 0xAA64,
+            Machine::Arm64Ec => 0xA641,
+        }
+    }
+
+    /// Machine of the import descriptor objects, which are always native code.
+    const fn native(&self) -> Self {
+        match self {
+            Machine::Arm64Ec => Machine::Arm64,
+            machine => *machine,
+        }
+    }
+
+    const fn is_64_bit(&self) -> bool {
+        !matches!(self, Machine::X86)
+    }
+
+    /// `IMAGE_REL_*_ADDR32NB` relocation type.
+    const fn rva_relocation(&self) -> u16 {
+        match self {
+            Machine::X86 => 7,
+            Machine::X64 => 3,
+            Machine::Arm64 | Machine::Arm64Ec => 2,
+        }
+    }
+}
+
+/// Problem found by [`ModuleDefinitionFile::to_import_library`] and [`ModuleDefinitionFile::from_import_library`].
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ImportLibraryError {
+    /// There is no `NAME` or `LIBRARY` statement to take the DLL name from.
+    MissingName,
+    /// Name is empty or contains a null character.
+    InvalidName(String),
+    /// `NONAME` export without an ordinal.
+    NonameWithoutOrdinal(String),
+    /// Ordinal is 0 or does not fit in 16 bits.
+    OrdinalOutOfRange(u64),
+    /// The library does not start with the archive signature or has an invalid member header.
+    InvalidArchive,
+    /// Short import object of the archive member at the offset is invalid.
+    InvalidImportObject(usize),
+    /// The library ends before the structure at the offset.
+    Truncated(usize),
+    /// The library would have more members than the second linker member can index,
+    /// which is at most 65535 including the 3 import descriptor objects.
+    TooManyMembers(usize),
+}
+
+impl Display for ImportLibraryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ImportLibraryError::MissingName => write!(f, "missing 'NAME' or 'LIBRARY' statement"),
+            ImportLibraryError::InvalidName(a) => write!(f, "invalid name '{a}'"),
+            ImportLibraryError::NonameWithoutOrdinal(a) => {
+                write!(f, "export '{a}' is 'NONAME' without an ordinal")
+            }
+            ImportLibraryError::OrdinalOutOfRange(a) => {
+                write!(f, "ordinal '{a}' is not between 1 and {MAX_ORDINAL}")
+            }
+            ImportLibraryError::InvalidArchive => write!(f, "invalid archive"),
+            ImportLibraryError::InvalidImportObject(a) => {
+                write!(f, "invalid import object at offset '{a:#X}'")
+            }
+            ImportLibraryError::Truncated(a) => write!(f, "library ends before offset '{a:#X}'"),
+            ImportLibraryError::TooManyMembers(a) => {
+                write!(
+                    f,
+                    "library has '{a}' members but at most {} are allowed",
+                    u16::MAX
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ImportLibraryError {}
+
+impl ModuleDefinitionFile {
+    /// Create a COFF import library (`.lib`) for linking against the module, like `lib.exe /DEF`.
+    ///
+    /// The library contains the import descriptor objects and one short import object for each export.
+    /// The DLL name is the `NAME` or `LIBRARY` name, with `.exe` or `.dll` added if it has no extension.
+    ///
+    /// * `PRIVATE` exports are left out.
+    /// * `DATA` exports only have the `__imp_` symbol, and `CONSTANT` exports are imported as constants.
+    /// * `NONAME` exports are imported by ordinal, other exports by name with the ordinal as hint.
+    /// * The [`name`](Export::name) is imported, since the [`internal_name`](Export::internal_name)
+    ///   only exists inside of the module.
+    /// * [`export_as`](Export::export_as) and [`import_alias`](Export::import_alias) import a different name
+    ///   from the module than the symbol in the library.
+    /// * [`argument_bytes`](Export::argument_bytes) decorate the symbol with [`Machine::X86`] and are ignored otherwise.
+    ///
+    /// The output is deterministic, timestamps are always 0.
+    ///
+    /// The objects in the library are the same as those written by `llvm-dlltool`,
+    /// but the archive also has the second linker member and the long names member of `lib.exe`
+    /// so the offsets of the objects differ.
+    /// Unlike `llvm-dlltool`, the stdcall decoration of [`Dialect::Gnu`](crate::Dialect::Gnu) files
+    /// is left out on other machines than [`Machine::X86`].
+    ///
+    /// # Errors
+    ///
+    /// If the file has no name, if an export is `NONAME` without a valid ordinal,
+    /// or if there are too many exports.
+    pub fn to_import_library(&self, machine: Machine) -> Result<Vec<u8>, ImportLibraryError> {
+        let name = self
+            .name
+            .as_deref()
+            .ok_or(ImportLibraryError::MissingName)?;
+        check_name(name)?;
+
+        let dll = if name.contains('.') {
+            String::from(name)
+        } else if self.is_library == Some(false) {
+            format!("{name}.exe")
+        } else {
+            format!("{name}.dll")
+        };
+        let library = dll.rsplit_once('.').map_or(dll.as_str(), |(stem, _)| stem);
+
+        let import_descriptor = format!("__IMPORT_DESCRIPTOR_{library}");
+        let null_thunk = format!("\x7f{library}_NULL_THUNK_DATA");
+
+        let mut members = vec![
+            Member {
+                data: import_descriptor_object(
+                    machine.native(),
+                    &dll,
+                    &import_descriptor,
+                    &null_thunk,
+                ),
+                symbols: vec![import_descriptor.clone()],
+                ec: false,
+            },
+            Member {
+                data: null_import_descriptor_object(machine.native()),
+                symbols: vec![String::from(NULL_IMPORT_DESCRIPTOR)],
+                ec: false,
+            },
+            Member {
+                data: null_thunk_object(machine.native(), &null_thunk),
+                symbols: vec![null_thunk.clone()],
+                ec: false,
+            },
+        ];
+
+        for export in self.exports.iter().filter(|e| !e.private) {
+            members.push(short_import(export, &dll, machine)?);
+        }
+
+        write_archive(&dll, &members)
+    }
+
+    /// Create a file from a COFF import library (`.lib`), such as one written by `lib.exe`
+    /// or [`to_import_library`](Self::to_import_library).
+    ///
+    /// `LIBRARY` is the DLL name without the `.dll` extension, or `NAME` for modules ending in `.exe`.
+    /// Short import objects for other modules than the first are skipped.
+    ///
+    /// The name type of short import objects becomes:
+    /// * `IMPORT_ORDINAL`: `NONAME` with the ordinal.
+    /// * `IMPORT_NAME` and `IMPORT_NAME_NOPREFIX`: only the [`name`](Export::name),
+    ///   without the underscore prefix on x86.
+    /// * `IMPORT_NAME_UNDECORATE`: the [`name`](Export::name) and [`argument_bytes`](Export::argument_bytes)
+    ///   of the symbol, with the undecorated name as [`import_alias`](Export::import_alias).
+    /// * `IMPORT_NAME_EXPORTAS`: [`export_as`](Export::export_as), unless it is the plain name of an ARM64EC function.
+    ///
+    /// The hint becomes the ordinal unless it is 0, and `DATA` and `CONST` imports
+    /// are [`data`](Export::data) and [`constant`](Export::constant).
+    ///
+    /// Long import objects, as written by GNU `dlltool`, are regular COFF objects
+    /// where only the name, the ordinal or hint and whether the import is data can be read.
+    /// Other objects in the library are skipped.
+    ///
+    /// # Errors
+    ///
+    /// If the archive or a short import object in it is invalid.
+    pub fn from_import_library(library: &[u8]) -> Result<Self, ImportLibraryError> {
+        let mut rest = library
+            .strip_prefix(ARCHIVE_SIGNATURE)
+            .ok_or(ImportLibraryError::InvalidArchive)?;
+        let mut offset = ARCHIVE_SIGNATURE.len();
+
+        let mut dll = None;
+        let mut exports = Vec::new();
+        while !rest.is_empty() {
+            let header = rest
+                .get(..MEMBER_HEADER_SIZE)
+                .ok_or(ImportLibraryError::Truncated(offset + MEMBER_HEADER_SIZE))?;
+            let size = core::str::from_utf8(&header[48..58])
+                .ok()
+                .and_then(|a| a.trim_end().parse::<usize>().ok())
+                .ok_or(ImportLibraryError::InvalidArchive)?;
+            let end = MEMBER_HEADER_SIZE
+                .checked_add(size)
+                .ok_or(ImportLibraryError::InvalidArchive)?;
+            let data = rest
+                .get(MEMBER_HEADER_SIZE..end)
+                .ok_or(ImportLibraryError::Truncated(offset.saturating_add(end)))?;
+
+            // Linker members, long names and EC symbols, but not long names of objects such as `/0`
+            if header[0] == b'/' && !header[1].is_ascii_digit() {
+                // Symbol maps are rebuilt from the members
+            } else if data.starts_with(&[0, 0, 0xFF, 0xFF]) {
+                let (module, export) = read_short_import(data)
+                    .ok_or(ImportLibraryError::InvalidImportObject(offset))?;
+                let module = utf8(module)?;
+
+                match &dll {
+                    None => {
+                        dll = Some(String::from(module));
+                        exports.push(export?);
+                    }
+                    Some(dll) if dll == module => exports.push(export?),
+                    Some(_) => {}
+                }
+            } else {
+                match read_long_import(data) {
+                    Some(LongImport::Module(module)) if dll.is_none() => {
+                        dll = Some(String::from(utf8(module)?));
+                    }
+                    Some(LongImport::Export(export)) => exports.push(export?),
+                    _ => {}
+                }
+            }
+
+            let end = end + size % 2;
+            rest = rest.get(end..).unwrap_or_default();
+            offset += end;
+        }
+
+        let (name, is_library) = match dll {
+            Some(dll) => match dll.rsplit_once('.') {
+                Some((name, extension)) if extension.eq_ignore_ascii_case("dll") => {
+                    (Some(String::from(name)), Some(true))
+                }
+                Some((name, extension)) if extension.eq_ignore_ascii_case("exe") => {
+                    (Some(String::from(name)), Some(false))
+                }
+                _ => (Some(dll), Some(true)),
+            },
+            None => (None, None),
+        };
+
+        Ok(Self {
+            name,
+            is_library,
+            exports,
+            ..Default::default()
+        })
+    }
+}
+
+struct Member {
+    data: Vec<u8>,
+    symbols: Vec<String>,
+    /// Symbols are placed in the `/<ECSYMBOLS>/` member instead of the linker members.
+    ec: bool,
+}
+
+fn check_name(name: &str) -> Result<(), ImportLibraryError> {
+    if name.is_empty() || name.contains('\0') {
+        return Err(ImportLibraryError::InvalidName(String::from(name)));
+    }
+
+    Ok(())
+}
+
+fn short_import(
+    export: &Export,
+    dll: &str,
+    machine: Machine,
+) -> Result<Member, ImportLibraryError> {
+    check_name(&export.name)?;
+
+    let import_type = if export.data {
+        IMPORT_DATA
+    } else if export.constant {
+        IMPORT_CONST
+    } else {
+        IMPORT_CODE
+    };
+
+    let hint = match export.ordinal {
+        Some(ordinal) if ordinal == 0 || ordinal > MAX_ORDINAL => {
+            return Err(ImportLibraryError::OrdinalOutOfRange(ordinal))
+        }
+        Some(ordinal) => ordinal as u16,
+        None if export.noname => {
+            return Err(ImportLibraryError::NonameWithoutOrdinal(
+                export.name.clone(),
+            ))
+        }
+        None => 0,
+    };
+
+    let mut symbol = export.name.clone();
+    let mut name_type = IMPORT_NAME;
+    if machine == Machine::X86 {
+        if let Some(argument_bytes) = export.argument_bytes {
+            symbol = format!("{symbol}@{argument_bytes}");
+        }
+
+        if !is_decorated(&export.name) {
+            symbol.insert(0, '_');
+            name_type = IMPORT_NAME_NOPREFIX;
+        }
+    }
+
+    let mut export_name = None;
+    if export.noname {
+        name_type = IMPORT_ORDINAL;
+    } else if let Some(export_as) = &export.export_as {
+        name_type = IMPORT_NAME_EXPORTAS;
+        export_name = Some(export_as.clone());
+    } else if let Some(import_alias) = &export.import_alias {
+        name_type = [IMPORT_NAME, IMPORT_NAME_NOPREFIX, IMPORT_NAME_UNDECORATE]
+            .into_iter()
+            .filter(|&a| a == IMPORT_NAME || machine == Machine::X86)
+            .find(|&a| apply_name_type(a, &symbol) == import_alias)
+            .unwrap_or(IMPORT_NAME_EXPORTAS);
+        if name_type == IMPORT_NAME_EXPORTAS {
+            export_name = Some(import_alias.clone());
+        }
+    }
+
+    let mut ec_symbols = None;
+    if machine == Machine::Arm64Ec && import_type == IMPORT_CODE {
+        let (mangled, demangled) = match arm64ec_mangle(&symbol) {
+            Some(mangled) => (mangled, symbol),
+            None => {
+                let demangled = arm64ec_demangle(&symbol).unwrap_or_else(|| symbol.clone());
+                (symbol, demangled)
+            }
+        };
+
+        if !export.noname && export_name.is_none() {
+            name_type = IMPORT_NAME_EXPORTAS;
+            export_name = Some(demangled.clone());
+        }
+
+        symbol = mangled;
+        ec_symbols = Some(demangled);
+    }
+
+    let size = symbol.len() + dll.len() + 2 + export_name.as_ref().map_or(0, |a| a.len() + 1);
+    let mut data = Vec::with_capacity(IMPORT_HEADER_SIZE + size);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, 0xFFFF);
+    push_u16(&mut data, 0);
+    push_u16(&mut data, machine.value());
+    push_u32(&mut data, 0);
+    push_u32(&mut data, size as u32);
+    push_u16(&mut data, hint);
+    push_u16(&mut data, import_type | name_type << 2);
+    push_str(&mut data, &symbol);
+    push_str(&mut data, dll);
+    if let Some(export_name) = &export_name {
+        push_str(&mut data, export_name);
+    }
+
+    let symbols = match ec_symbols {
+        Some(demangled) => vec![
+            format!("__imp_{demangled}"),
+            demangled.clone(),
+            format!("__imp_aux_{demangled}"),
+            symbol,
+        ],
+        None if import_type == IMPORT_DATA => vec![format!("__imp_{symbol}")],
+        None => vec![format!("__imp_{symbol}"), symbol],
+    };
+
+    Ok(Member {
+        data,
+        symbols,
+        ec: machine == Machine::Arm64Ec,
+    })
+}
+
+/// Decorated names are not prefixed with an underscore on x86.
+fn is_decorated(name: &str) -> bool {
+    name.starts_with('?') || name.contains('@')
+}
+
+/// Name the loader imports from the module for the symbol in the library.
+pub(crate) fn apply_name_type(name_type: u16, symbol: &str) -> &str {
+    match name_type {
+        IMPORT_NAME_NOPREFIX => symbol.trim_start_matches(['?', '@', '_']),
+        IMPORT_NAME_UNDECORATE => {
+            let symbol = symbol.trim_start_matches(['?', '@', '_']);
+            symbol.split('@').next().unwrap_or(symbol)
+        }
+        _ => symbol,
+    }
+}
+
+/// `#name` for C functions and `?name@@$$h...` for C++ functions.
+fn arm64ec_mangle(name: &str) -> Option<String> {
+    if let Some(rest) = name.strip_prefix('?') {
+        if rest.contains("$$h") {
+            return None;
+        }
+
+        let insert = match name.find("@@") {
+            Some(i) if name.find("@@@") != Some(i) => i + 2,
+            _ => name.find('@').map_or(name.len(), |i| i + 1),
+        };
+        return Some(format!("{}$$h{}", &name[..insert], &name[insert..]));
+    }
+
+    if name.starts_with('#') {
+        return None;
+    }
+
+    Some(format!("#{name}"))
+}
+
+pub(crate) fn arm64ec_demangle(name: &str) -> Option<String> {
+    if let Some(name) = name.strip_prefix('#') {
+        return Some(String::from(name));
+    }
+
+    if name.starts_with('?') {
+        let i = name.find("$$h")?;
+        return Some(format!("{}{}", &name[..i], &name[i + 3..]));
+    }
+
+    None
+}
+
+fn import_descriptor_object(
+    machine: Machine,
+    dll: &str,
+    import_descriptor: &str,
+    null_thunk: &str,
+) -> Vec<u8> {
+    const SECTIONS: usize = 2;
+    const RELOCATIONS: usize = 3;
+    const RELOCATION_SIZE: usize = 10;
+
+    let idata2 = FILE_HEADER_SIZE + SECTIONS * SECTION_HEADER_SIZE;
+    let relocations = idata2 + IMPORT_DIRECTORY_SIZE;
+    let idata6 = relocations + RELOCATIONS * RELOCATION_SIZE;
+    let symbols = idata6 + dll.len() + 1;
+
+    let mut data = Vec::new();
+    file_header(&mut data, machine, SECTIONS, symbols, 7);
+
+    let characteristics = IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE;
+    section_header(
+        &mut data,
+        b".idata$2",
+        IMPORT_DIRECTORY_SIZE,
+        idata2,
+        relocations,
+        RELOCATIONS,
+        IMAGE_SCN_ALIGN_4BYTES | characteristics,
+    );
+    section_header(
+        &mut data,
+        b".idata$6",
+        dll.len() + 1,
+        idata6,
+        0,
+        0,
+        IMAGE_SCN_ALIGN_2BYTES | characteristics,
+    );
+
+    data.extend_from_slice(&[0; IMPORT_DIRECTORY_SIZE]);
+
+    // Name, import lookup table and import address table of the import directory entry
+    for (offset, symbol) in [(12, 2), (0, 3), (16, 4)] {
+        push_u32(&mut data, offset);
+        push_u32(&mut data, symbol);
+        push_u16(&mut data, machine.rva_relocation());
+    }
+
+    push_str(&mut data, dll);
+
+    let null_import_descriptor = 4 + import_descriptor.len() + 1;
+    let null_thunk_offset = null_import_descriptor + NULL_IMPORT_DESCRIPTOR.len() + 1;
+    symbol(
+        &mut data,
+        SymbolName::Offset(4),
+        1,
+        IMAGE_SYM_CLASS_EXTERNAL,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Short(b".idata$2"),
+        1,
+        IMAGE_SYM_CLASS_SECTION,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Short(b".idata$6"),
+        2,
+        IMAGE_SYM_CLASS_STATIC,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Short(b".idata$4"),
+        0,
+        IMAGE_SYM_CLASS_SECTION,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Short(b".idata$5"),
+        0,
+        IMAGE_SYM_CLASS_SECTION,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Offset(null_import_descriptor),
+        0,
+        IMAGE_SYM_CLASS_EXTERNAL,
+    );
+    symbol(
+        &mut data,
+        SymbolName::Offset(null_thunk_offset),
+        0,
+        IMAGE_SYM_CLASS_EXTERNAL,
+    );
+
+    string_table(
+        &mut data,
+        &[import_descriptor, NULL_IMPORT_DESCRIPTOR, null_thunk],
+    );
+
+    data
+}
+
+fn null_import_descriptor_object(machine: Machine) -> Vec<u8> {
+    let idata3 = FILE_HEADER_SIZE + SECTION_HEADER_SIZE;
+
+    let mut data = Vec::new();
+    file_header(&mut data, machine, 1, idata3 + IMPORT_DIRECTORY_SIZE, 1);
+    section_header(
+        &mut data,
+        b".idata$3",
+        IMPORT_DIRECTORY_SIZE,
+        idata3,
+        0,
+        0,
+        IMAGE_SCN_ALIGN_4BYTES
+            | IMAGE_SCN_CNT_INITIALIZED_DATA
+            | IMAGE_SCN_MEM_READ
+            | IMAGE_SCN_MEM_WRITE,
+    );
+    data.extend_from_slice(&[0; IMPORT_DIRECTORY_SIZE]);
+
+    symbol(
+        &mut data,
+        SymbolName::Offset(4),
+        1,
+        IMAGE_SYM_CLASS_EXTERNAL,
+    );
+    string_table(&mut data, &[NULL_IMPORT_DESCRIPTOR]);
+
+    data
+}
+
+fn null_thunk_object(machine: Machine, null_thunk: &str) -> Vec<u8> {
+    let (size, alignment) = if machine.is_64_bit() {
+        (8, IMAGE_SCN_ALIGN_8BYTES)
+    } else {
+        (4, IMAGE_SCN_ALIGN_4BYTES)
+    };
+    let characteristics =
+        alignment | IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE;
+    let idata5 = FILE_HEADER_SIZE + 2 * SECTION_HEADER_SIZE;
+    let idata4 = idata5 + size;
+
+    let mut data = Vec::new();
+    file_header(&mut data, machine, 2, idata4 + size, 1);
+    section_header(&mut data, b".idata$5", size, idata5, 0, 0, characteristics);
+    section_header(&mut data, b".idata$4", size, idata4, 0, 0, characteristics);
+    data.resize(data.len() + 2 * size, 0);
+
+    symbol(
+        &mut data,
+        SymbolName::Offset(4),
+        1,
+        IMAGE_SYM_CLASS_EXTERNAL,
+    );
+    string_table(&mut data, &[null_thunk]);
+
+    data
+}
+
+fn file_header(data: &mut Vec<u8>, machine: Machine, sections: usize, symbols: usize, count: u32) {
+    push_u16(data, machine.value());
+    push_u16(data, sections as u16);
+    push_u32(data, 0);
+    push_u32(data, symbols as u32);
+    push_u32(data, count);
+    push_u16(data, 0);
+    push_u16(
+        data,
+        if machine.is_64_bit() {
+            0
+        } else {
+            IMAGE_FILE_32BIT_MACHINE
+        },
+    );
+}
+
+fn section_header(
+    data: &mut Vec<u8>,
+    name: &[u8; 8],
+    size: usize,
+    offset: usize,
+    relocations: usize,
+    relocation_count: usize,
+    characteristics: u32,
+) {
+    data.extend_from_slice(name);
+    push_u32(data, 0);
+    push_u32(data, 0);
+    push_u32(data, size as u32);
+    push_u32(data, offset as u32);
+    push_u32(data, relocations as u32);
+    push_u32(data, 0);
+    push_u16(data, relocation_count as u16);
+    push_u16(data, 0);
+    push_u32(data, characteristics);
+}
+
+enum SymbolName<'a> {
+    Short(&'a [u8; 8]),
+    /// Offset into the string table.
+    Offset(usize),
+}
+
+fn symbol(data: &mut Vec<u8>, name: SymbolName<'_>, section: u16, class: u8) {
+    match name {
+        SymbolName::Short(name) => data.extend_from_slice(name),
+        SymbolName::Offset(offset) => {
+            push_u32(data, 0);
+            push_u32(data, offset as u32);
+        }
+    }
+    push_u32(data, 0);
+    push_u16(data, section);
+    push_u16(data, 0);
+    data.push(class);
+    data.push(0);
+}
+
+fn string_table(data: &mut Vec<u8>, strings: &[&str]) {
+    let size = 4 + strings.iter().map(|a| a.len() + 1).sum::<usize>();
+    push_u32(data, size as u32);
+    for s in strings {
+        push_str(data, s);
+    }
+}
+
+/// Archive in the format of `lib.exe` with both linker members and a long names member,
+/// unlike the GNU archives of `llvm-dlltool` which only have the first linker member.
+fn write_archive(dll: &str, members: &[Member]) -> Result<Vec<u8>, ImportLibraryError> {
+    // Members are indexed from 1 with a `u16`
+    if members.len() > usize::from(u16::MAX) {
+        return Err(ImportLibraryError::TooManyMembers(members.len()));
+    }
+
+    let member_name = if dll.len() > MAX_MEMBER_NAME {
+        String::from("/0")
+    } else {
+        format!("{dll}/")
+    };
+    let long_names = if dll.len() > MAX_MEMBER_NAME {
+        format!("{dll}\0")
+    } else {
+        String::new()
+    };
+
+    let symbols = members
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| !m.ec)
+        .flat_map(|(i, m)| m.symbols.iter().map(move |s| (s.as_str(), i)))
+        .collect::<Vec<_>>();
+    let ec_symbols = members
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.ec)
+        .flat_map(|(i, m)| m.symbols.iter().map(move |s| (s.as_str(), i)))
+        .collect::<BTreeMap<_, _>>();
+    let sorted_symbols = symbols.iter().copied().collect::<BTreeMap<_, _>>();
+
+    let names_len =
+        |symbols: &mut dyn Iterator<Item = &str>| symbols.map(|a| a.len() + 1).sum::<usize>();
+    let first_linker_size = 4 + symbols.len() * 4 + names_len(&mut symbols.iter().map(|a| a.0));
+    let second_linker_size = 4
+        + members.len() * 4
+        + 4
+        + sorted_symbols.len() * 2
+        + names_len(&mut sorted_symbols.keys().copied());
+    let ec_symbols_size = 4 + ec_symbols.len() * 2 + names_len(&mut ec_symbols.keys().copied());
+
+    let mut offset = ARCHIVE_SIGNATURE.len()
+        + padded(first_linker_size)
+        + padded(second_linker_size)
+        + padded(long_names.len());
+    if !ec_symbols.is_empty() {
+        offset += padded(ec_symbols_size);
+    }
+    let offsets = members
+        .iter()
+        .map(|m| {
+            let member = offset;
+            offset += padded(m.data.len());
+            member as u32
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = Vec::with_capacity(offset);
+    out.extend_from_slice(ARCHIVE_SIGNATURE);
+
+    member_header(&mut out, "/", first_linker_size, "0");
+    out.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+    for (_, member) in &symbols {
+        out.extend_from_slice(&offsets[*member].to_be_bytes());
+    }
+    for (symbol, _) in &symbols {
+        push_str(&mut out, symbol);
+    }
+    pad(&mut out);
+
+    member_header(&mut out, "/", second_linker_size, "0");
+    push_u32(&mut out, offsets.len() as u32);
+    for offset in &offsets {
+        push_u32(&mut out, *offset);
+    }
+    push_u32(&mut out, sorted_symbols.len() as u32);
+    for member in sorted_symbols.values() {
+        push_u16(&mut out, *member as u16 + 1);
+    }
+    for symbol in sorted_symbols.keys() {
+        push_str(&mut out, symbol);
+    }
+    pad(&mut out);
+
+    if !ec_symbols.is_empty() {
+        member_header(&mut out, "/<ECSYMBOLS>/", ec_symbols_size, "0");
+        push_u32(&mut out, ec_symbols.len() as u32);
+        for member in ec_symbols.values() {
+            push_u16(&mut out, *member as u16 + 1);
+        }
+        for symbol in ec_symbols.keys() {
+            push_str(&mut out, symbol);
+        }
+        pad(&mut out);
+    }
+
+    member_header(&mut out, "//", long_names.len(), "0");
+    out.extend_from_slice(long_names.as_bytes());
+    pad(&mut out);
+
+    for member in members {
+        member_header(&mut out, &member_name, member.data.len(), "644");
+        out.extend_from_slice(&member.data);
+        pad(&mut out);
+    }
+
+    Ok(out)
+}
+
+fn member_header(out: &mut Vec<u8>, name: &str, size: usize, mode: &str) {
+    let header = format!("{name:<16}{:<12}{:<6}{:<6}{mode:<8}{size:<10}`\n", 0, 0, 0);
+    out.extend_from_slice(header.as_bytes());
+}
+
+/// Members start on even offsets.
+const fn padded(size: usize) -> usize {
+    MEMBER_HEADER_SIZE + size + size % 2
+}
+
+fn pad(out: &mut Vec<u8>) {
+    if out.len() % 2 != 0 {
+        out.push(b'\n');
+    }
+}
+
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_str(data: &mut Vec<u8>, s: &str) {
+    data.extend_from_slice(s.as_bytes());
+    data.push(0);
+}
+
+fn utf8(s: &[u8]) -> Result<&str, ImportLibraryError> {
+    core::str::from_utf8(s)
+        .map_err(|_| ImportLibraryError::InvalidName(String::from_utf8_lossy(s).into_owned()))
+}
+
+/// Module name and export of a short import object.
+fn read_short_import(data: &[u8]) -> Option<(&[u8], Result<Export, ImportLibraryError>)> {
+    let machine = read_u16(data, 6)?;
+    let hint = read_u16(data, 16)?;
+    let info = read_u16(data, 18)?;
+
+    let mut strings = data.get(IMPORT_HEADER_SIZE..)?.split(|&a| a == 0);
+    let symbol = strings.next()?;
+    let module = strings.next()?;
+    let export_name = strings.next().filter(|a| !a.is_empty());
+
+    let export = (|| {
+        let symbol = utf8(symbol)?;
+        let export_name = export_name.map(utf8).transpose()?;
+
+        let import_type = info & 0b11;
+        let name_type = info >> 2 & 0b111;
+        let x86 = machine == Machine::X86.value();
+
+        let mut name = symbol;
+        let ec_name;
+        if machine == Machine::Arm64Ec.value() && import_type == IMPORT_CODE {
+            if let Some(demangled) = arm64ec_demangle(symbol) {
+                ec_name = demangled;
+                name = &ec_name;
+            }
+        }
+
+        // Undecorated names get an underscore prefix on x86, after the argument bytes are added
+        let mut argument_bytes = None;
+        let mut default_name_type = IMPORT_NAME;
+        if let Some(undecorated) = name.strip_prefix('_').filter(|_| x86) {
+            let (undecorated, bytes) = match split_stdcall_decoration(undecorated) {
+                Some((undecorated, bytes)) => (undecorated, Some(bytes)),
+                None => (undecorated, None),
+            };
+
+            if !is_decorated(undecorated) {
+                name = undecorated;
+                argument_bytes = bytes;
+                default_name_type = IMPORT_NAME_NOPREFIX;
+            }
+        }
+
+        let mut export = Export::new(
+            String::from(name),
+            None,
+            (hint != 0).then_some(u64::from(hint)),
+            name_type == IMPORT_ORDINAL,
+            false,
+            import_type == IMPORT_DATA,
+        )
+        .with_argument_bytes(argument_bytes)
+        .with_constant(import_type == IMPORT_CONST);
+
+        match name_type {
+            IMPORT_ORDINAL => {}
+            IMPORT_NAME_EXPORTAS => {
+                if let Some(export_as) = export_name.filter(|&a| a != export.name) {
+                    export = export.with_export_as(Some(String::from(export_as)));
+                }
+            }
+            name_type if name_type != default_name_type => {
+                let alias = apply_name_type(name_type, symbol);
+                export = export.with_import_alias(Some(String::from(alias)));
+            }
+            _ => {}
+        }
+
+        Ok(export)
+    })();
+
+    Some((module, export))
+}
+
+enum LongImport<'a> {
+    Module(&'a [u8]),
+    Export(Result<Export, ImportLibraryError>),
+}
+
+/// Regular COFF object that is part of an import library.
+fn read_long_import<'a>(data: &'a [u8]) -> Option<LongImport<'a>> {
+    let machine = read_u16(data, 0)?;
+    let sections = usize::from(read_u16(data, 2)?);
+    let symbol_table = read_u32(data, 8)? as usize;
+    let symbols = read_u32(data, 12)? as usize;
+    let section_table = FILE_HEADER_SIZE + usize::from(read_u16(data, 16)?);
+
+    let mut has_code = false;
+    let mut import_descriptor = false;
+    let mut idata = [None; 8];
+    for i in 0..sections {
+        let header = data.get(section_table + i * SECTION_HEADER_SIZE..)?;
+        let name = header.get(..8)?;
+        let size = read_u32(header, 16)? as usize;
+        let offset = read_u32(header, 20)? as usize;
+        let characteristics = read_u32(header, 36)?;
+
+        has_code |= characteristics & IMAGE_SCN_CNT_CODE != 0;
+        if let Some(&[index]) = name.strip_prefix(b".idata$") {
+            if let Some(section) = idata.get_mut(usize::from(index.wrapping_sub(b'0'))) {
+                let section_data = offset
+                    .checked_add(size)
+                    .and_then(|end| data.get(offset..end));
+                *section = Some(section_data.unwrap_or_default());
+                import_descriptor |= index == b'2';
+            }
+        }
+    }
+
+    let c_str = |s: &'a [u8]| s.split(|&a| a == 0).next().unwrap_or_default();
+
+    // `lib.exe` import descriptor or GNU `dlltool` tail object
+    if let Some(module) = idata[6].filter(|_| import_descriptor) {
+        return Some(LongImport::Module(c_str(module)));
+    }
+    if let Some(module) = idata[7].map(c_str).filter(|a| !a.is_empty()) {
+        return Some(LongImport::Module(module));
+    }
+
+    let thunk = idata[4].or(idata[5])?;
+    let x86 = machine == Machine::X86.value();
+
+    let (name, hint, noname) = if let Some(hint_name) = idata[6] {
+        let hint = read_u16(hint_name, 0)?;
+        (c_str(hint_name.get(2..)?), hint, false)
+    } else {
+        // Ordinal imports have the highest bit of the thunk set
+        let (ordinal, flag) = match *thunk {
+            [a, b, .., c] if thunk.len() == 4 || thunk.len() == 8 => {
+                (u16::from_le_bytes([a, b]), c)
+            }
+            _ => return None,
+        };
+        if flag & 0x80 == 0 {
+            return None;
+        }
+
+        // The number of symbols is only bounded by the size of the object
+        let strings = symbols
+            .checked_mul(SYMBOL_SIZE)
+            .and_then(|a| a.checked_add(symbol_table))?;
+        let table = data.get(symbol_table..)?.chunks_exact(SYMBOL_SIZE);
+        let name = table.take(symbols).find_map(|symbol| {
+            let name = match symbol.get(..8)? {
+                [0, 0, 0, 0, ..] => {
+                    let name = strings.checked_add(read_u32(symbol, 4)? as usize)?;
+                    c_str(data.get(name..)?)
+                }
+                name => c_str(name),
+            };
+            name.strip_prefix(b"__imp_")
+        })?;
+        let name = match name {
+            [b'_', name @ ..] if x86 => name,
+            name => name,
+        };
+
+        (name, ordinal, true)
+    };
+
+    let export = utf8(name).map(|name| {
+        Export::new(
+            String::from(name),
+            None,
+            (hint != 0).then_some(u64::from(hint)),
+            noname,
+            false,
+            !has_code,
+        )
+    });
+
+    Some(LongImport::Export(export))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    match data.get(offset..offset.checked_add(2)?)? {
+        &[a, b] => Some(u16::from_le_bytes([a, b])),
+        _ => None,
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    match data.get(offset..offset.checked_add(4)?)? {
+        &[a, b, c, d] => Some(u32::from_le_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::Span;
+use crate::options::ParseOptions;
+use crate::parse_ref::{COMMENT, DOUBLE_QUOTE};
+use crate::token_iterator::{is_keyword, TokenIterator, BYTE_ORDER_MARK};
+
+/// Kind of [`Token`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum TokenKind {
+    /// Reserved word such as `EXPORTS`, or attribute such as `READ`.
+    Keyword,
+    /// Name that is not a keyword, number or ordinal.
+    ///
+    /// Module and entry names such as `kernel32.CreateFileA` are a single identifier.
+    Identifier,
+    /// String enclosed in double quotes, including the quotes.
+    ///
+    /// Embedded quotes are written as two double quotes (`""`).
+    /// An unterminated string continues until the end of the source.
+    QuotedString,
+    /// Decimal or `0x` prefixed hexadecimal number.
+    Number,
+    /// `@` followed by a digit or nothing, which is not necessarily a valid number as in `@1.2`.
+    ///
+    /// Other words starting with `@` are identifiers, such as the fastcall name `@func@8`.
+    Ordinal,
+    /// `=`
+    Equals,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+    /// `.` between the numbers of `VERSION 1.2`.
+    ///
+    /// Periods anywhere else are part of an identifier or number, as in `HEAPSIZE 1.5`.
+    Period,
+    /// Comment starting with `;` until the end of the line, not including the newline.
+    Comment,
+    /// Either `\n` or `\r\n`.
+    Newline,
+}
+
+/// Single token returned by [`Lexer`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Token<'a> {
+    /// Kind of token.
+    pub kind: TokenKind,
+    /// Text of the token exactly as written in the source.
+    pub text: &'a str,
+    /// Byte span of [`text`](Self::text) in the source.
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    /// Create new [`Token`].
+    pub const fn new(kind: TokenKind, text: &'a str, span: Span) -> Self {
+        Self { kind, text, span }
+    }
+
+    /// The [`text`](Self::text) without the surrounding quotes of a [`TokenKind::QuotedString`].
+    ///
+    /// Embedded quotes are kept as two double quotes (`""`).
+    pub fn unquoted(&self) -> &'a str {
+        if self.kind != TokenKind::QuotedString {
+            return self.text;
+        }
+
+        let s = self.text.strip_prefix(DOUBLE_QUOTE).unwrap_or(self.text);
+        s.strip_suffix(DOUBLE_QUOTE).unwrap_or(s)
+    }
+}
+
+/// Iterator over the [`Token`]s of a file.
+///
+/// Whitespace other than newlines and a byte order mark at the start are skipped.
+/// Lexing can not fail, so invalid files will still produce tokens.
+///
+/// Tokens are split exactly like the parser splits them.
+///
+/// ```rust
+/// # use msvc_def::{Lexer, Span, Token, TokenKind};
+/// let mut lexer = Lexer::new("VERSION 1.2 ; comment\n");
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Keyword, "VERSION", Span::new(0, 7))));
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Number, "1", Span::new(8, 9))));
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Period, ".", Span::new(9, 10))));
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Number, "2", Span::new(10, 11))));
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Comment, "; comment", Span::new(12, 21))));
+/// assert_eq!(lexer.next(), Some(Token::new(TokenKind::Newline, "\n", Span::new(21, 22))));
+/// assert_eq!(lexer.next(), None);
+/// ```
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Lexer<'a> {
+    source: &'a str,
+    it: TokenIterator<'a>,
+    offset: usize,
+    next: Option<Token<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Create new [`Lexer`].
+    pub fn new(source: &'a str) -> Self {
+        Self::with_options(source, ParseOptions::new())
+    }
+
+    /// Create new [`Lexer`] with custom [`ParseOptions`].
+    ///
+    /// With [`ParseOptions::case_insensitive`] keywords are recognized regardless of ASCII case.
+    pub fn with_options(source: &'a str, options: ParseOptions) -> Self {
+        Self {
+            source,
+            it: TokenIterator::new(source, options),
+            offset: 0,
+            next: None,
+        }
+    }
+
+    /// Next token that is not a comment or newline, from the same iterator as the parser uses.
+    fn significant(&mut self) -> Option<Token<'a>> {
+        let after_version = self.it.version_token_encountered_tokens_ago != 0;
+        let (start, token) = self.it.eat_token_spanned()?;
+
+        // The closing quote is not part of the token
+        let mut end = start + token.len();
+        if token.starts_with(DOUBLE_QUOTE) && self.source[end..].starts_with(DOUBLE_QUOTE) {
+            end += DOUBLE_QUOTE.len();
+        }
+        let text = &self.source[start..end];
+
+        let kind = match text {
+            "=" => TokenKind::Equals,
+            ":" => TokenKind::Colon,
+            "," => TokenKind::Comma,
+            "." if after_version => TokenKind::Period,
+            _ if text.starts_with(DOUBLE_QUOTE) => TokenKind::QuotedString,
+            _ if is_keyword(self.it.keyword(token)) => TokenKind::Keyword,
+            _ if is_number(text) => TokenKind::Number,
+            _ => match text.strip_prefix('@') {
+                Some(ordinal)
+                    if ordinal.is_empty() || ordinal.starts_with(|a: char| a.is_ascii_digit()) =>
+                {
+                    TokenKind::Ordinal
+                }
+                _ => TokenKind::Identifier,
+            },
+        };
+
+        Some(Token::new(kind, text, Span::new(start, end)))
+    }
+
+    /// Newline or comment before `end`, after skipping whitespace.
+    fn trivia(&mut self, end: usize) -> Option<Token<'a>> {
+        let gap = &self.source[self.offset..end];
+        let start = gap
+            .char_indices()
+            .find(|&(i, a)| {
+                a == '\n'
+                    || gap[i..].starts_with("\r\n")
+                    || !(a.is_whitespace() || a == BYTE_ORDER_MARK)
+            })
+            .map_or(gap.len(), |(i, _)| i);
+        let rest = &gap[start..];
+
+        let (kind, len) = if rest.is_empty() {
+            self.offset = end;
+            return None;
+        } else if rest.starts_with("\r\n") {
+            (TokenKind::Newline, 2)
+        } else if rest.starts_with('\n') {
+            (TokenKind::Newline, 1)
+        } else if rest.starts_with(COMMENT) {
+            (
+                TokenKind::Comment,
+                rest.find(['\r', '\n']).unwrap_or(rest.len()),
+            )
+        } else {
+            // Never skipped by the parser, but kept so that lexing always makes progress
+            let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (TokenKind::Identifier, len)
+        };
+
+        let start = self.offset + start;
+        self.offset = start + len;
+        Some(Token::new(
+            kind,
+            &self.source[start..start + len],
+            Span::new(start, start + len),
+        ))
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_none() {
+            self.next = self.significant();
+        }
+
+        let end = self.next.map_or(self.source.len(), |a| a.span.start);
+        if let Some(trivia) = self.trivia(end) {
+            return Some(trivia);
+        }
+
+        let token = self.next.take()?;
+        self.offset = token.span.end;
+        Some(token)
+    }
+}
+
+fn is_number(s: &str) -> bool {
+    match s.strip_prefix("0x") {
+        Some(hex) => !hex.is_empty() && hex.bytes().all(|a| a.is_ascii_hexdigit()),
+        None => !s.is_empty() && s.bytes().all(|a| a.is_ascii_digit()),
+    }
+}
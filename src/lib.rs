@@ -53,8 +53,11 @@ assert_eq!(export.get(1), None);
 //!
 //! # Features
 //!
-//! * `alloc`: Adds [`ModuleDefinitionFile`].
-//! * `std`: Adds [`Error`](core::error::Error) support for [`ParseError`]. Enables `alloc` feature.
+//! * `alloc`: Adds [`ModuleDefinitionFile`], the lossless [`SyntaxTree`], reading exports of PE images with [`ModuleDefinitionFile::from_pe`]
+//!   reading and writing import libraries with [`ModuleDefinitionFile::from_import_library`] and [`ModuleDefinitionFile::to_import_library`],
+//!   GNU `ld` version scripts with [`ModuleDefinitionFile::to_version_script`]
+//!   and Apple exported symbols lists with [`ModuleDefinitionFile::to_exported_symbols_list`].
+//! * `std`: Adds [`Error`](core::error::Error) support for [`ParseError`] and [`IoWriter`] for writing to a [`std::io::Write`]. Enables `alloc` feature.
 //!
 //! # Notes
 //!
@@ -64,7 +67,6 @@ assert_eq!(export.get(1), None);
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![no_std]
 #![forbid(unsafe_code)]
-#![forbid(unsafe_code)]
 #![warn(
     clippy::perf,
     clippy::correctness,
@@ -85,21 +87,71 @@ extern crate alloc;
 
 use crate::parse_ref::parse_ref_inner;
 
+#[cfg(feature = "alloc")]
+mod builder;
+#[cfg(feature = "alloc")]
+mod cst;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "alloc")]
+mod elf;
 mod error;
+#[cfg(feature = "alloc")]
+mod import_library;
+mod lexer;
+#[cfg(feature = "alloc")]
+mod mach_o;
+#[cfg(feature = "alloc")]
+mod merge;
+mod options;
+#[cfg(feature = "alloc")]
+mod ordinals;
 
 #[cfg(feature = "alloc")]
 mod parse;
 mod parse_ref;
+#[cfg(feature = "alloc")]
+mod pe;
 mod token_iterator;
+mod validate;
+mod write;
 
 #[cfg(test)]
 mod test;
 
 pub use error::*;
-pub use parse_ref::{ExportRef, Exports, ModuleDefinitionFileRef, SectionRef, Sections};
+pub use lexer::{Lexer, Token, TokenKind};
+pub use options::{
+    Dialect, ExportOrder, Indent, KeywordCase, LineEnding, ParseOptions, Radix, WriteOptions,
+};
+pub use parse_ref::{
+    ExportRef, ExportTarget, Exports, ImportRef, Imports, ModuleDefinitionFileRef, SectionRef,
+    Sections,
+};
+pub use validate::{Lint, LintKind};
+#[cfg(feature = "std")]
+pub use write::IoWriter;
 
+#[cfg(feature = "alloc")]
+pub use builder::{BuildError, ModuleDefinitionFileBuilder};
+#[cfg(feature = "alloc")]
+pub use cst::{ExportNode, SyntaxKind, SyntaxToken, SyntaxTree};
+#[cfg(feature = "alloc")]
+pub use diff::{diff, Change, ChangeKind};
+#[cfg(feature = "alloc")]
+pub use elf::ElfError;
+#[cfg(feature = "alloc")]
+pub use import_library::{ImportLibraryError, Machine};
+#[cfg(feature = "alloc")]
+pub use mach_o::{ExportedSymbolsList, MachOOptions, MachOWarning};
+#[cfg(feature = "alloc")]
+pub use merge::{ConflictPolicy, MergeConflict, MergeOptions};
+#[cfg(feature = "alloc")]
+pub use ordinals::{OrdinalAssignment, OrdinalStrategy};
 #[cfg(feature = "alloc")]
 pub use parse::*;
+#[cfg(feature = "alloc")]
+pub use pe::PeError;
 
 /// Parse without using `alloc`.
 ///
@@ -107,7 +159,68 @@ pub use parse::*;
 ///
 /// If the file format is invalid, those described by [`ParseErrorKind`].
 pub fn parse_ref(s: &str) -> Result<ModuleDefinitionFileRef<'_>, ParseError<'_>> {
-    parse_ref_inner(s)
+    parse_ref_inner(s, ParseOptions::new(), false, &mut |_| {})
+}
+
+/// Parse without using `alloc` with custom [`ParseOptions`].
+///
+/// `warnings` is called for every unknown statement and stray token that is skipped.
+/// If [`ParseOptions::strict`] is enabled they are returned as errors instead.
+///
+/// # Errors
+///
+/// If the file format is invalid, those described by [`ParseErrorKind`].
+pub fn parse_ref_with_options<'a>(
+    s: &'a str,
+    options: ParseOptions,
+    mut warnings: impl FnMut(ParseError<'a>),
+) -> Result<ModuleDefinitionFileRef<'a>, ParseError<'a>> {
+    parse_ref_inner(s, options, false, &mut |d| warnings(d.error))
+}
+
+/// Parse without using `alloc`, continuing after errors.
+///
+/// After an error the parser resynchronizes at the next statement or line.
+/// Every error and warning is passed to `diagnostics`, including errors in
+/// [`Sections`], [`Exports`] and [`Imports`].
+/// The returned file contains everything that could be parsed,
+/// but the item iterators will still return their errors.
+///
+/// Whether unknown statements are errors or warnings is decided by [`ParseOptions::strict`].
+pub fn parse_ref_recovering<'a>(
+    s: &'a str,
+    options: ParseOptions,
+    mut diagnostics: impl FnMut(Diagnostic<'a>),
+) -> ModuleDefinitionFileRef<'a> {
+    let file = match parse_ref_inner(s, options, true, &mut diagnostics) {
+        Ok(file) => file,
+        Err(_) => unreachable!("errors are passed to diagnostics when recovering"),
+    };
+
+    for e in file.errors() {
+        diagnostics(Diagnostic::error(e));
+    }
+
+    file
+}
+
+/// Parse without using `alloc` and check every item in
+/// [`Sections`], [`Exports`] and [`Imports`] for errors.
+///
+/// [`parse_ref`] parses items lazily, so errors in them are only reported when iterating.
+/// Use [`ModuleDefinitionFileRef::errors`] in order to get all errors instead of only the first.
+///
+/// # Errors
+///
+/// If the file format is invalid, the first in the file of those described by [`ParseErrorKind`].
+pub fn parse_ref_strict(s: &str) -> Result<ModuleDefinitionFileRef<'_>, ParseError<'_>> {
+    let file = parse_ref(s)?;
+
+    if let Some(e) = file.errors().next() {
+        return Err(e);
+    }
+
+    Ok(file)
 }
 
 /// Parse with `alloc`.
@@ -116,6 +229,58 @@ pub fn parse_ref(s: &str) -> Result<ModuleDefinitionFileRef<'_>, ParseError<'_>>
 ///
 /// If the file format is invalid, those described by [`ParseErrorKind`].
 #[cfg(feature = "alloc")]
-pub fn parse(s: &str) -> Result<ModuleDefinitionFile, ParseError> {
-    parse_inner(s)
+pub fn parse(s: &str) -> Result<ModuleDefinitionFile, ParseError<'_>> {
+    parse_inner(parse_ref(s)?, &mut Err)
+}
+
+/// Parse with `alloc` with custom [`ParseOptions`].
+///
+/// Returns the file along with a warning for every unknown statement and stray token that is skipped.
+/// If [`ParseOptions::strict`] is enabled they are returned as errors instead.
+///
+/// # Errors
+///
+/// If the file format is invalid, those described by [`ParseErrorKind`].
+#[cfg(feature = "alloc")]
+pub fn parse_with_options(
+    s: &str,
+    options: ParseOptions,
+) -> Result<(ModuleDefinitionFile, alloc::vec::Vec<ParseError<'_>>), ParseError<'_>> {
+    let mut warnings = alloc::vec::Vec::new();
+    let file = parse_ref_inner(s, options, false, &mut |d| warnings.push(d.error))?;
+
+    Ok((parse_inner(file, &mut Err)?, warnings))
+}
+
+/// Parse with `alloc`, continuing after errors.
+///
+/// After an error the parser resynchronizes at the next statement or line.
+/// Returns everything that could be parsed, along with all errors and warnings sorted by location.
+/// Invalid items in [`sections`](ModuleDefinitionFile::sections), [`exports`](ModuleDefinitionFile::exports)
+/// and [`imports`](ModuleDefinitionFile::imports) are left out.
+///
+/// Whether unknown statements are errors or warnings is decided by [`ParseOptions::strict`].
+#[cfg(feature = "alloc")]
+pub fn parse_recovering(
+    s: &str,
+    options: ParseOptions,
+) -> (ModuleDefinitionFile, alloc::vec::Vec<Diagnostic<'_>>) {
+    let mut diagnostics = alloc::vec::Vec::new();
+
+    let file = match parse_ref_inner(s, options, true, &mut |d| diagnostics.push(d)) {
+        Ok(file) => file,
+        Err(_) => unreachable!("errors are passed to diagnostics when recovering"),
+    };
+
+    let file = parse_inner(file, &mut |e| {
+        diagnostics.push(Diagnostic::error(e));
+        Ok(())
+    });
+    let Ok(file) = file else {
+        unreachable!("item errors are passed to diagnostics when recovering")
+    };
+
+    diagnostics.sort_by_key(|a| a.error.span);
+
+    (file, diagnostics)
 }
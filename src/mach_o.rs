@@ -0,0 +1,156 @@
+use crate::{ExportTarget, ModuleDefinitionFile};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// Options for [`ModuleDefinitionFile::to_exported_symbols_list`].
+///
+/// ```rust
+/// # use msvc_def::MachOOptions;
+/// let options = MachOOptions::new().alias_internal_names(true);
+/// assert!(options.is_alias_internal_names());
+/// ```
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct MachOOptions {
+    alias_internal_names: bool,
+}
+
+impl MachOOptions {
+    /// Create new [`MachOOptions`] where internal names are ignored.
+    pub const fn new() -> Self {
+        Self {
+            alias_internal_names: false,
+        }
+    }
+
+    /// Add `-alias` arguments for exports with an [`internal_name`](crate::Export::internal_name) in the module,
+    /// so that `entryname=internal_name` exports `entryname` from the symbol `internal_name`.
+    ///
+    /// Without this the object files must define a symbol for every `entryname` themselves.
+    pub const fn alias_internal_names(mut self, alias_internal_names: bool) -> Self {
+        self.alias_internal_names = alias_internal_names;
+        self
+    }
+
+    /// Returns [`true`] if internal names are turned into `-alias` arguments.
+    pub const fn is_alias_internal_names(&self) -> bool {
+        self.alias_internal_names
+    }
+}
+
+/// Construct without an equivalent in Mach-O, found by [`ModuleDefinitionFile::to_exported_symbols_list`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum MachOWarning<'a> {
+    /// Export with an ordinal, which is ignored since symbols are only exported by name.
+    Ordinal {
+        /// Name of the export.
+        name: &'a str,
+        /// Ordinal of the export.
+        ordinal: u64,
+    },
+    /// `NONAME` export, which is still exported by name.
+    Noname(&'a str),
+    /// `PRIVATE` export, which is still exported since there are no import libraries.
+    Private(&'a str),
+    /// Export forwarded to another module, which is left out.
+    Forwarded(&'a str),
+    /// Section in `SECTIONS`, whose attributes are ignored.
+    Section(&'a str),
+}
+
+impl Display for MachOWarning<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MachOWarning::Ordinal { name, ordinal } => {
+                write!(f, "ordinal '{ordinal}' of export '{name}' is ignored")
+            }
+            MachOWarning::Noname(a) => {
+                write!(f, "export '{a}' is 'NONAME' but is exported by name")
+            }
+            MachOWarning::Private(a) => {
+                write!(f, "export '{a}' is 'PRIVATE' but is exported")
+            }
+            MachOWarning::Forwarded(a) => {
+                write!(
+                    f,
+                    "export '{a}' is forwarded to another module and is left out"
+                )
+            }
+            MachOWarning::Section(a) => {
+                write!(f, "attributes of section '{a}' are ignored")
+            }
+        }
+    }
+}
+
+/// Linker input for Mach-O created by [`ModuleDefinitionFile::to_exported_symbols_list`].
+#[derive(Debug, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ExportedSymbolsList<'a> {
+    /// File for `ld -exported_symbols_list` with one symbol per line.
+    pub list: String,
+    /// Arguments for `ld`, with three for every alias such as `-alias _internal _entryname`.
+    ///
+    /// Empty unless [`MachOOptions::alias_internal_names`] is enabled.
+    pub alias_arguments: Vec<String>,
+    /// Constructs that have no equivalent, in the order they appear in the file.
+    pub warnings: Vec<MachOWarning<'a>>,
+}
+
+impl ModuleDefinitionFile {
+    /// Create an exported symbols list for the Apple linker, which makes the exports the only
+    /// global symbols of a dynamic library.
+    ///
+    /// Every export is prefixed with an underscore, since C names are mangled that way on Apple platforms.
+    /// Exports forwarded to another module are left out.
+    ///
+    /// ```rust
+    /// # use msvc_def::{MachOOptions, MachOWarning};
+    /// let file = msvc_def::parse("LIBRARY foo\nEXPORTS\n    func @1\n    alias=func\n").unwrap();
+    ///
+    /// let list = file.to_exported_symbols_list(MachOOptions::new().alias_internal_names(true));
+    /// assert_eq!(list.list, "_func\n_alias\n");
+    /// assert_eq!(list.alias_arguments, ["-alias", "_func", "_alias"]);
+    /// assert_eq!(list.warnings, [MachOWarning::Ordinal { name: "func", ordinal: 1 }]);
+    /// ```
+    pub fn to_exported_symbols_list(&self, options: MachOOptions) -> ExportedSymbolsList<'_> {
+        let mut list = ExportedSymbolsList::default();
+
+        for export in &self.exports {
+            let name = export.name.as_str();
+            if let Some(ordinal) = export.ordinal {
+                list.warnings.push(MachOWarning::Ordinal { name, ordinal });
+            }
+            if export.noname {
+                list.warnings.push(MachOWarning::Noname(name));
+            }
+            if export.private {
+                list.warnings.push(MachOWarning::Private(name));
+            }
+
+            match export.target() {
+                ExportTarget::Symbol(symbol) => {
+                    if options.alias_internal_names && symbol != name {
+                        list.alias_arguments.push(String::from("-alias"));
+                        list.alias_arguments.push(format!("_{symbol}"));
+                        list.alias_arguments.push(format!("_{name}"));
+                    }
+                }
+                ExportTarget::ForwardName { .. } | ExportTarget::ForwardOrdinal { .. } => {
+                    list.warnings.push(MachOWarning::Forwarded(name));
+                    continue;
+                }
+            }
+
+            list.list.push('_');
+            list.list.push_str(name);
+            list.list.push('\n');
+        }
+
+        for section in &self.sections {
+            list.warnings.push(MachOWarning::Section(&section.name));
+        }
+
+        list
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{Export, ModuleDefinitionFile};
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+
+/// What to do when two files disagree in [`ModuleDefinitionFile::merge`].
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ConflictPolicy {
+    /// Return a [`MergeConflict`].
+    #[default]
+    Error,
+    /// Keep the value from the file being merged into.
+    FirstWins,
+    /// Use the value from the file being merged in.
+    LastWins,
+}
+
+/// Options for [`ModuleDefinitionFile::merge`].
+///
+/// By default every conflict is an error.
+///
+/// ```rust
+/// # use msvc_def::{ConflictPolicy, MergeOptions};
+/// let options = MergeOptions::new()
+///     .all(ConflictPolicy::FirstWins)
+///     .ordinals(ConflictPolicy::Error);
+/// assert_eq!(options.get_name(), ConflictPolicy::FirstWins);
+/// assert_eq!(options.get_ordinals(), ConflictPolicy::Error);
+/// ```
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct MergeOptions {
+    name: ConflictPolicy,
+    heap_size: ConflictPolicy,
+    stack_size: ConflictPolicy,
+    base_address: ConflictPolicy,
+    version: ConflictPolicy,
+    ordinals: ConflictPolicy,
+    exports: ConflictPolicy,
+}
+
+impl MergeOptions {
+    /// Create new [`MergeOptions`] where every conflict is an error.
+    pub const fn new() -> Self {
+        Self {
+            name: ConflictPolicy::Error,
+            heap_size: ConflictPolicy::Error,
+            stack_size: ConflictPolicy::Error,
+            base_address: ConflictPolicy::Error,
+            version: ConflictPolicy::Error,
+            ordinals: ConflictPolicy::Error,
+            exports: ConflictPolicy::Error,
+        }
+    }
+
+    /// Use `policy` for every kind of conflict.
+    pub const fn all(self, policy: ConflictPolicy) -> Self {
+        Self {
+            name: policy,
+            heap_size: policy,
+            stack_size: policy,
+            base_address: policy,
+            version: policy,
+            ordinals: policy,
+            exports: policy,
+        }
+    }
+
+    /// Policy for different `NAME` or `LIBRARY` statements.
+    pub const fn name(mut self, policy: ConflictPolicy) -> Self {
+        self.name = policy;
+        self
+    }
+
+    /// Policy for different `HEAPSIZE` statements.
+    pub const fn heap_size(mut self, policy: ConflictPolicy) -> Self {
+        self.heap_size = policy;
+        self
+    }
+
+    /// Policy for different `STACKSIZE` statements.
+    pub const fn stack_size(mut self, policy: ConflictPolicy) -> Self {
+        self.stack_size = policy;
+        self
+    }
+
+    /// Policy for different `BASE` arguments.
+    pub const fn base_address(mut self, policy: ConflictPolicy) -> Self {
+        self.base_address = policy;
+        self
+    }
+
+    /// Policy for different `VERSION` statements.
+    pub const fn version(mut self, policy: ConflictPolicy) -> Self {
+        self.version = policy;
+        self
+    }
+
+    /// Policy for an export with different ordinals, or different exports with the same ordinal.
+    ///
+    /// When different exports have the same ordinal, the losing export has its ordinal removed.
+    pub const fn ordinals(mut self, policy: ConflictPolicy) -> Self {
+        self.ordinals = policy;
+        self
+    }
+
+    /// Policy for an export with a different internal name or attributes.
+    pub const fn exports(mut self, policy: ConflictPolicy) -> Self {
+        self.exports = policy;
+        self
+    }
+
+    /// Policy for different `NAME` or `LIBRARY` statements.
+    pub const fn get_name(&self) -> ConflictPolicy {
+        self.name
+    }
+
+    /// Policy for different `HEAPSIZE` statements.
+    pub const fn get_heap_size(&self) -> ConflictPolicy {
+        self.heap_size
+    }
+
+    /// Policy for different `STACKSIZE` statements.
+    pub const fn get_stack_size(&self) -> ConflictPolicy {
+        self.stack_size
+    }
+
+    /// Policy for different `BASE` arguments.
+    pub const fn get_base_address(&self) -> ConflictPolicy {
+        self.base_address
+    }
+
+    /// Policy for different `VERSION` statements.
+    pub const fn get_version(&self) -> ConflictPolicy {
+        self.version
+    }
+
+    /// Policy for an export with different ordinals, or different exports with the same ordinal.
+    pub const fn get_ordinals(&self) -> ConflictPolicy {
+        self.ordinals
+    }
+
+    /// Policy for an export with a different internal name or attributes.
+    pub const fn get_exports(&self) -> ConflictPolicy {
+        self.exports
+    }
+}
+
+/// Conflict that [`ModuleDefinitionFile::merge`] was told to treat as an error.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum MergeConflict {
+    /// Different `NAME` or `LIBRARY` statements.
+    Name,
+    /// Different `HEAPSIZE` statements.
+    HeapSize,
+    /// Different `STACKSIZE` statements.
+    StackSize,
+    /// Different `BASE` arguments.
+    BaseAddress,
+    /// Different `VERSION` statements.
+    Version,
+    /// The export has different ordinals.
+    Ordinal(String),
+    /// Different exports have the same ordinal.
+    DuplicateOrdinal(u64),
+    /// The export has a different internal name or attributes.
+    Export(String),
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MergeConflict::Name => write!(f, "conflicting 'NAME' or 'LIBRARY' statements"),
+            MergeConflict::HeapSize => write!(f, "conflicting 'HEAPSIZE' statements"),
+            MergeConflict::StackSize => write!(f, "conflicting 'STACKSIZE' statements"),
+            MergeConflict::BaseAddress => write!(f, "conflicting 'BASE' arguments"),
+            MergeConflict::Version => write!(f, "conflicting 'VERSION' statements"),
+            MergeConflict::Ordinal(a) => write!(f, "export '{a}' has conflicting ordinals"),
+            MergeConflict::DuplicateOrdinal(a) => {
+                write!(f, "ordinal '{a}' is used by different exports")
+            }
+            MergeConflict::Export(a) => write!(f, "export '{a}' has conflicting definitions"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MergeConflict {}
+
+impl ModuleDefinitionFile {
+    /// Merge `other` into this file.
+    ///
+    /// [`exports`](Self::exports), [`sections`](Self::sections) and [`imports`](Self::imports) are combined,
+    /// with identical items only appearing once and the attributes of sections with the same name combined.
+    /// Single values that are only set in one of the files are kept,
+    /// and conflicting values are resolved according to `options`.
+    /// `STUB` and `DESCRIPTION` are kept from the first file that has them.
+    ///
+    /// ```rust
+    /// # use msvc_def::{ConflictPolicy, MergeOptions, MergeConflict};
+    /// let mut file = msvc_def::parse("LIBRARY a\nEXPORTS\n    f @1\n").unwrap();
+    /// let other = msvc_def::parse("HEAPSIZE 4096\nEXPORTS\n    g @1\n").unwrap();
+    ///
+    /// assert_eq!(file.merge(&other, MergeOptions::new()), Err(MergeConflict::DuplicateOrdinal(1)));
+    ///
+    /// let options = MergeOptions::new().ordinals(ConflictPolicy::FirstWins);
+    /// file.merge(&other, options).unwrap();
+    /// assert_eq!(file.heap_reserve, Some(4096));
+    /// assert_eq!(file.exports[0].ordinal, Some(1));
+    /// assert_eq!(file.exports[1].ordinal, None);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the first conflict whose policy is [`ConflictPolicy::Error`].
+    /// The file is not changed in case of error.
+    pub fn merge(&mut self, other: &Self, options: MergeOptions) -> Result<(), MergeConflict> {
+        let mut merged = self.clone();
+
+        let mut name = merged.name.take().map(|a| (a, merged.is_library));
+        resolve(
+            &mut name,
+            other.name.clone().map(|a| (a, other.is_library)),
+            options.name,
+            || MergeConflict::Name,
+        )?;
+        (merged.name, merged.is_library) = match name {
+            Some((name, is_library)) => (Some(name), is_library),
+            None => (None, merged.is_library.or(other.is_library)),
+        };
+
+        let mut heap = merged.heap_reserve.map(|a| (a, merged.heap_commit));
+        resolve(
+            &mut heap,
+            other.heap_reserve.map(|a| (a, other.heap_commit)),
+            options.heap_size,
+            || MergeConflict::HeapSize,
+        )?;
+        (merged.heap_reserve, merged.heap_commit) = split(heap);
+
+        let mut stack = merged.stack_reserve.map(|a| (a, merged.stack_commit));
+        resolve(
+            &mut stack,
+            other.stack_reserve.map(|a| (a, other.stack_commit)),
+            options.stack_size,
+            || MergeConflict::StackSize,
+        )?;
+        (merged.stack_reserve, merged.stack_commit) = split(stack);
+
+        resolve(
+            &mut merged.base_address,
+            other.base_address,
+            options.base_address,
+            || MergeConflict::BaseAddress,
+        )?;
+
+        let mut version = merged.major_version.map(|a| (a, merged.minor_version));
+        resolve(
+            &mut version,
+            other.major_version.map(|a| (a, other.minor_version)),
+            options.version,
+            || MergeConflict::Version,
+        )?;
+        (merged.major_version, merged.minor_version) = split(version);
+
+        if merged.stub.is_none() {
+            merged.stub = other.stub.clone();
+        }
+        if merged.description.is_none() {
+            merged.description = other.description.clone();
+        }
+
+        for export in &other.exports {
+            merge_export(&mut merged, export, options)?;
+        }
+
+        for section in &other.sections {
+            match merged.sections.iter_mut().find(|a| a.name == section.name) {
+                Some(s) => {
+                    s.read |= section.read;
+                    s.write |= section.write;
+                    s.execute |= section.execute;
+                    s.shared |= section.shared;
+                }
+                None => merged.sections.push(section.clone()),
+            }
+        }
+
+        for import in &other.imports {
+            if !merged.imports.contains(import) {
+                merged.imports.push(import.clone());
+            }
+        }
+
+        *self = merged;
+        Ok(())
+    }
+
+    /// Merge all `files` in order, as if by calling [`merge`](Self::merge) on the first file with each following file.
+    ///
+    /// Returns an empty file if `files` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first conflict whose policy is [`ConflictPolicy::Error`].
+    pub fn merge_all<'a>(
+        files: impl IntoIterator<Item = &'a Self>,
+        options: MergeOptions,
+    ) -> Result<Self, MergeConflict> {
+        let mut merged = Self::default();
+        for file in files {
+            merged.merge(file, options)?;
+        }
+
+        Ok(merged)
+    }
+}
+
+fn merge_export(
+    merged: &mut ModuleDefinitionFile,
+    export: &Export,
+    options: MergeOptions,
+) -> Result<(), MergeConflict> {
+    let index = match merged.exports.iter().position(|a| a.name == export.name) {
+        Some(index) => {
+            let existing = &mut merged.exports[index];
+
+            let same_definition = existing.internal_name == export.internal_name
+                && existing.noname == export.noname
+                && existing.private == export.private
+                && existing.data == export.data
+                && existing.argument_bytes == export.argument_bytes
+                && existing.import_alias == export.import_alias
+                && existing.constant == export.constant
+                && existing.export_as == export.export_as;
+            if !same_definition {
+                let ordinal = existing.ordinal;
+                if choose(options.exports, || {
+                    MergeConflict::Export(export.name.clone())
+                })? {
+                    *existing = export.clone();
+                    existing.ordinal = ordinal;
+                }
+            }
+
+            resolve(
+                &mut existing.ordinal,
+                export.ordinal,
+                options.ordinals,
+                || MergeConflict::Ordinal(export.name.clone()),
+            )?;
+
+            index
+        }
+        None => {
+            merged.exports.push(export.clone());
+            merged.exports.len() - 1
+        }
+    };
+
+    let Some(ordinal) = merged.exports[index].ordinal else {
+        return Ok(());
+    };
+
+    let other = merged
+        .exports
+        .iter()
+        .enumerate()
+        .position(|(i, a)| i != index && a.ordinal == Some(ordinal));
+    if let Some(other) = other {
+        if choose(options.ordinals, || {
+            MergeConflict::DuplicateOrdinal(ordinal)
+        })? {
+            merged.exports[other].ordinal = None;
+        } else {
+            merged.exports[index].ordinal = None;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns [`true`] if the value from the last file should be used.
+fn choose(
+    policy: ConflictPolicy,
+    conflict: impl FnOnce() -> MergeConflict,
+) -> Result<bool, MergeConflict> {
+    match policy {
+        ConflictPolicy::Error => Err(conflict()),
+        ConflictPolicy::FirstWins => Ok(false),
+        ConflictPolicy::LastWins => Ok(true),
+    }
+}
+
+fn resolve<T: PartialEq>(
+    first: &mut Option<T>,
+    last: Option<T>,
+    policy: ConflictPolicy,
+    conflict: impl FnOnce() -> MergeConflict,
+) -> Result<(), MergeConflict> {
+    let Some(last) = last else {
+        return Ok(());
+    };
+
+    match first {
+        Some(f) if *f == last => {}
+        Some(_) => {
+            if choose(policy, conflict)? {
+                *first = Some(last);
+            }
+        }
+        None => *first = Some(last),
+    }
+
+    Ok(())
+}
+
+fn split<A, B>(value: Option<(A, Option<B>)>) -> (Option<A>, Option<B>) {
+    match value {
+        Some((a, b)) => (Some(a), b),
+        None => (None, None),
+    }
+}
@@ -0,0 +1,326 @@
+/// Options for parsing.
+///
+/// The default options are the same as used by [`parse_ref`](crate::parse_ref) and [`parse`](crate::parse).
+///
+/// ```rust
+/// # use msvc_def::ParseOptions;
+/// let options = ParseOptions::new().strict(true);
+/// assert!(msvc_def::parse_ref_with_options("EXPORT myfunc", options, |_| {}).is_err());
+///
+/// let options = ParseOptions::new();
+/// let mut warnings = 0;
+/// assert!(msvc_def::parse_ref_with_options("EXPORT myfunc", options, |_| warnings += 1).is_ok());
+/// assert_eq!(warnings, 1);
+/// ```
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ParseOptions {
+    strict: bool,
+    case_insensitive: bool,
+    dialect: Dialect,
+}
+
+impl ParseOptions {
+    /// Create new [`ParseOptions`] with the default lenient behavior.
+    pub const fn new() -> Self {
+        Self {
+            strict: false,
+            case_insensitive: false,
+            dialect: Dialect::Msvc,
+        }
+    }
+
+    /// Return [`ParseErrorKind::UnknownStatement`](crate::ParseErrorKind::UnknownStatement)
+    /// and [`ParseErrorKind::UnexpectedToken`](crate::ParseErrorKind::UnexpectedToken) as errors
+    /// instead of reporting them as warnings and skipping them.
+    pub const fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns [`true`] if unknown statements and stray tokens are errors.
+    pub const fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Recognize keywords such as `EXPORTS`, `Library` and `data` regardless of ASCII case.
+    ///
+    /// By default keywords must be upper case, and anything else is parsed as a name.
+    /// Only recognition is affected, the original spelling is kept by [`SyntaxTree`](crate::SyntaxTree)
+    /// and in errors such as [`ParseErrorKind::UnknownStatement`](crate::ParseErrorKind::UnknownStatement).
+    ///
+    /// ```rust
+    /// # use msvc_def::{ExportRef, ParseOptions};
+    /// let options = ParseOptions::new().case_insensitive(true);
+    /// let file = msvc_def::parse_ref_with_options("Library mylib\nexports\n    myfunc data\n", options, |_| {}).unwrap();
+    /// assert_eq!(file.name, Some("mylib"));
+    ///
+    /// let mut exports = file.exports;
+    /// assert_eq!(exports.next(), Some(Ok(ExportRef::new("myfunc", None, None, false, false, true))));
+    /// ```
+    pub const fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Returns [`true`] if keywords are recognized regardless of ASCII case.
+    pub const fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Syntax of items in `EXPORTS`.
+    ///
+    /// ```rust
+    /// # use msvc_def::{Dialect, ExportRef, ParseOptions};
+    /// let options = ParseOptions::new().dialect(Dialect::Gnu);
+    /// let file = msvc_def::parse_ref_with_options("EXPORTS\n    func@12 == _func CONSTANT\n", options, |_| {}).unwrap();
+    ///
+    /// let export = ExportRef::new("func", None, None, false, false, false)
+    ///     .with_argument_bytes(Some(12))
+    ///     .with_import_alias(Some("_func"))
+    ///     .with_constant(true);
+    /// let mut exports = file.exports;
+    /// assert_eq!(exports.next(), Some(Ok(export)));
+    /// ```
+    pub const fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Syntax of items in `EXPORTS`.
+    pub const fn get_dialect(&self) -> Dialect {
+        self.dialect
+    }
+}
+
+/// Syntax used by [`ParseOptions::dialect`] and [`WriteOptions::dialect`].
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Dialect {
+    /// Syntax accepted by the Microsoft LINK.
+    ///
+    /// `func@12` is an ordinary name and `CONSTANT` is the start of the next export.
+    #[default]
+    Msvc,
+    /// Syntax accepted by MinGW `dlltool` and GNU `ld`.
+    ///
+    /// * `func@12` is split into the name `func` and the
+    ///   [`argument_bytes`](crate::ExportRef::argument_bytes) of the stdcall decoration.
+    /// * `name == import_name` sets the [`import_alias`](crate::ExportRef::import_alias).
+    /// * `CONSTANT` sets [`constant`](crate::ExportRef::constant).
+    Gnu,
+}
+
+/// Options for writing.
+///
+/// The default options are the same as used by
+/// [`ModuleDefinitionFileRef::write_to_buffer`](crate::ModuleDefinitionFileRef::write_to_buffer).
+///
+/// ```rust
+/// # use msvc_def::{ExportOrder, Indent, KeywordCase, Radix, WriteOptions};
+/// let file = msvc_def::parse_ref("LIBRARY lib BASE=0x1000\nEXPORTS\n    b @2\n    long_name=inner @1 DATA\n").unwrap();
+///
+/// let options = WriteOptions::new()
+///     .indent(Indent::Spaces(2))
+///     .radix(Radix::Decimal)
+///     .align_ordinals(true)
+///     .export_order(ExportOrder::Ordinal);
+///
+/// let mut buf = [0_u8; 256];
+/// let s = file.write_to_buffer_with_options(&mut buf, options).unwrap().unwrap();
+/// assert_eq!(s, "LIBRARY lib BASE=4096\nEXPORTS\n  long_name=inner @1 DATA\n  b               @2\n");
+/// ```
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct WriteOptions {
+    indent: Indent,
+    radix: Radix,
+    align_ordinals: bool,
+    spaces_around_equals: bool,
+    keyword_case: KeywordCase,
+    export_order: ExportOrder,
+    line_ending: LineEnding,
+    dialect: Dialect,
+}
+
+impl WriteOptions {
+    /// Create new [`WriteOptions`] with 4 spaces of indentation, hexadecimal numbers and `\n` line endings.
+    pub const fn new() -> Self {
+        Self {
+            indent: Indent::Spaces(4),
+            radix: Radix::Hexadecimal,
+            align_ordinals: false,
+            spaces_around_equals: false,
+            keyword_case: KeywordCase::Upper,
+            export_order: ExportOrder::Source,
+            line_ending: LineEnding::Lf,
+            dialect: Dialect::Msvc,
+        }
+    }
+
+    /// Indentation of items in `SECTIONS`, `EXPORTS` and `IMPORTS`.
+    pub const fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Radix of the `BASE`, `HEAPSIZE` and `STACKSIZE` arguments.
+    ///
+    /// Ordinals and versions are always decimal.
+    pub const fn radix(mut self, radix: Radix) -> Self {
+        self.radix = radix;
+        self
+    }
+
+    /// Pad export names so that the `@ordinal` of every export starts in the same column.
+    ///
+    /// With [`Indent::Tab`] the padding is made of tabs, assuming a tab width of 4.
+    pub const fn align_ordinals(mut self, align_ordinals: bool) -> Self {
+        self.align_ordinals = align_ordinals;
+        self
+    }
+
+    /// Write `name = internal_name` instead of `name=internal_name` in `EXPORTS` and `IMPORTS`.
+    pub const fn spaces_around_equals(mut self, spaces_around_equals: bool) -> Self {
+        self.spaces_around_equals = spaces_around_equals;
+        self
+    }
+
+    /// Case of statements and attributes.
+    pub const fn keyword_case(mut self, keyword_case: KeywordCase) -> Self {
+        self.keyword_case = keyword_case;
+        self
+    }
+
+    /// Order of the items in `EXPORTS`.
+    pub const fn export_order(mut self, export_order: ExportOrder) -> Self {
+        self.export_order = export_order;
+        self
+    }
+
+    /// Line ending written after every line.
+    pub const fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Syntax of items in `EXPORTS`.
+    ///
+    /// The stdcall decoration of [`argument_bytes`](crate::ExportRef::argument_bytes) is always written,
+    /// [`import_alias`](crate::ExportRef::import_alias) and [`constant`](crate::ExportRef::constant)
+    /// are only written with [`Dialect::Gnu`].
+    pub const fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Indentation of items in `SECTIONS`, `EXPORTS` and `IMPORTS`.
+    pub const fn get_indent(&self) -> Indent {
+        self.indent
+    }
+
+    /// Radix of the `BASE`, `HEAPSIZE` and `STACKSIZE` arguments.
+    pub const fn get_radix(&self) -> Radix {
+        self.radix
+    }
+
+    /// Returns [`true`] if the `@ordinal` of exports are aligned.
+    pub const fn get_align_ordinals(&self) -> bool {
+        self.align_ordinals
+    }
+
+    /// Returns [`true`] if `=` is surrounded by spaces in `EXPORTS` and `IMPORTS`.
+    pub const fn get_spaces_around_equals(&self) -> bool {
+        self.spaces_around_equals
+    }
+
+    /// Case of statements and attributes.
+    pub const fn get_keyword_case(&self) -> KeywordCase {
+        self.keyword_case
+    }
+
+    /// Order of the items in `EXPORTS`.
+    pub const fn get_export_order(&self) -> ExportOrder {
+        self.export_order
+    }
+
+    /// Line ending written after every line.
+    pub const fn get_line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Syntax of items in `EXPORTS`.
+    pub const fn get_dialect(&self) -> Dialect {
+        self.dialect
+    }
+}
+
+/// Indentation used by [`WriteOptions::indent`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Indent {
+    /// A number of spaces.
+    Spaces(u8),
+    /// A single tab.
+    Tab,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+/// Radix used by [`WriteOptions::radix`].
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Radix {
+    /// `0x1000`
+    #[default]
+    Hexadecimal,
+    /// `4096`
+    Decimal,
+}
+
+/// Case used by [`WriteOptions::keyword_case`].
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum KeywordCase {
+    /// `EXPORTS`
+    #[default]
+    Upper,
+    /// `exports`
+    ///
+    /// Keywords are matched case-sensitively by default,
+    /// so files written this way must be parsed with [`ParseOptions::case_insensitive`].
+    /// Names that are keywords in any case are always quoted, so they are not read as keywords.
+    Lower,
+}
+
+/// Order used by [`WriteOptions::export_order`].
+///
+/// Without the `alloc` feature, sorting parses the exports again for every export,
+/// which takes quadratic time.
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ExportOrder {
+    /// Same order as in the file.
+    #[default]
+    Source,
+    /// Sorted by [`name`](crate::ExportRef::name).
+    Name,
+    /// Sorted by [`ordinal`](crate::ExportRef::ordinal), with exports without an ordinal last.
+    Ordinal,
+}
+
+/// Line ending used by [`WriteOptions::line_ending`].
+#[derive(Debug, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    pub(crate) const fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
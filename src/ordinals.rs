@@ -0,0 +1,108 @@
+use crate::validate::MAX_ORDINAL;
+use crate::ModuleDefinitionFile;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+/// How [`ModuleDefinitionFile::assign_ordinals`] picks ordinals for exports without one.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OrdinalStrategy<'a> {
+    /// Count upwards from the highest ordinal in the file, or from 1 if there are none.
+    AppendAfterHighest,
+    /// Use the lowest ordinals that are not already in use, starting from 1.
+    FillGaps,
+    /// Use the ordinal of the export with the same name in a previous version of the file.
+    ///
+    /// Exports that are new, or whose previous ordinal is already in use or invalid, are appended after the highest
+    /// ordinal of both files so that ordinals of removed exports are never reused.
+    FromPrevious(&'a ModuleDefinitionFile),
+}
+
+/// Ordinal given to an export by [`ModuleDefinitionFile::assign_ordinals`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct OrdinalAssignment {
+    /// Index of the export in [`exports`](ModuleDefinitionFile::exports).
+    pub index: usize,
+    /// The assigned ordinal.
+    pub ordinal: u64,
+}
+
+impl OrdinalAssignment {
+    /// Create new [`OrdinalAssignment`].
+    pub const fn new(index: usize, ordinal: u64) -> Self {
+        Self { index, ordinal }
+    }
+}
+
+impl ModuleDefinitionFile {
+    /// Give an ordinal to every export that does not have one.
+    ///
+    /// Existing ordinals are never changed.
+    /// Returns the assignments made, in the order of [`exports`](Self::exports).
+    ///
+    /// Only ordinals up to 65535 are assigned, and existing ordinals above it are ignored
+    /// when finding the highest ordinal, use [`validate`](Self::validate) to find them.
+    /// Exports are left without an ordinal once there are no more ordinals to assign.
+    ///
+    /// ```rust
+    /// # use msvc_def::{OrdinalAssignment, OrdinalStrategy};
+    /// let mut file = msvc_def::parse("EXPORTS\n    a @2\n    b\n").unwrap();
+    ///
+    /// let assignments = file.assign_ordinals(OrdinalStrategy::FillGaps);
+    /// assert_eq!(assignments, [OrdinalAssignment::new(1, 1)]);
+    /// assert_eq!(file.exports[1].ordinal, Some(1));
+    /// ```
+    pub fn assign_ordinals(&mut self, strategy: OrdinalStrategy<'_>) -> Vec<OrdinalAssignment> {
+        let mut used: BTreeSet<u64> = self.exports.iter().filter_map(|e| e.ordinal).collect();
+
+        let mut next = match strategy {
+            OrdinalStrategy::AppendAfterHighest => highest(&used) + 1,
+            OrdinalStrategy::FillGaps => 1,
+            OrdinalStrategy::FromPrevious(previous) => {
+                let previous = previous.exports.iter().filter_map(|e| e.ordinal);
+                let previous = previous.filter(|&o| o <= MAX_ORDINAL).max();
+                highest(&used).max(previous.unwrap_or(0)) + 1
+            }
+        };
+
+        let mut assignments = Vec::new();
+        for (index, export) in self.exports.iter_mut().enumerate() {
+            if export.ordinal.is_some() {
+                continue;
+            }
+
+            let previous = match strategy {
+                OrdinalStrategy::FromPrevious(previous) => previous
+                    .exports
+                    .iter()
+                    .find(|e| e.name == export.name)
+                    .and_then(|e| e.ordinal)
+                    .filter(|o| (1..=MAX_ORDINAL).contains(o) && !used.contains(o)),
+                _ => None,
+            };
+
+            let ordinal = match previous {
+                Some(ordinal) => ordinal,
+                None => {
+                    while next <= MAX_ORDINAL && used.contains(&next) {
+                        next += 1;
+                    }
+                    if next > MAX_ORDINAL {
+                        continue;
+                    }
+                    next
+                }
+            };
+
+            used.insert(ordinal);
+            export.ordinal = Some(ordinal);
+            assignments.push(OrdinalAssignment::new(index, ordinal));
+        }
+
+        assignments
+    }
+}
+
+/// Highest valid ordinal, so that counting upwards from it can not overflow.
+fn highest(used: &BTreeSet<u64>) -> u64 {
+    used.range(..=MAX_ORDINAL).next_back().copied().unwrap_or(0)
+}
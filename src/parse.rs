@@ -1,9 +1,17 @@
-use crate::{parse_ref, ParseError};
-use alloc::string::{String, ToString};
+use crate::write::{write_file, Header, Length};
+use crate::{
+    ExportRef, ExportTarget, ImportRef, Lint, ModuleDefinitionFileRef, ParseError, SectionRef,
+    WriteError, WriteOptions,
+};
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
 
 /// Owned version of [`ModuleDefinitionFileRef`](crate::ModuleDefinitionFileRef).
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+///
+/// Names and strings are stored without the surrounding quotes and with embedded quotes as a single double quote (`"`).
+/// Quotes are added when writing if needed.
+#[derive(Debug, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ModuleDefinitionFile {
     /// Name specified by either the `NAME` or `LIBRARY` statements.
     pub name: Option<String>,
@@ -30,6 +38,11 @@ pub struct ModuleDefinitionFile {
     /// `When used in a module definition file that builds a virtual device driver (VxD), allows you to specify a file name that contains an IMAGE_DOS_HEADER structure (defined in WINNT.H) to be used in the virtual device driver (VxD), rather than the default header.`
     pub stub: Option<String>,
 
+    /// `Writes a string into an .rdata section.`
+    ///
+    /// Embedded quotes are stored as a single double quote (`"`).
+    pub description: Option<String>,
+
     /// `Tells LINK to put a number in the header of the .exe file or DLL. The default is version 0.`
     pub major_version: Option<u16>,
     /// `Tells LINK to put a number in the header of the .exe file or DLL. The default is version 0.`
@@ -39,6 +52,8 @@ pub struct ModuleDefinitionFile {
     pub sections: Vec<Section>,
     /// `Introduces a section of one or more export definitions that specify the exported names or ordinals of functions or data.`
     pub exports: Vec<Export>,
+    /// Legacy statement that introduces a section of one or more functions imported from other modules.
+    pub imports: Vec<Import>,
 }
 
 impl ModuleDefinitionFile {
@@ -51,13 +66,30 @@ impl ModuleDefinitionFile {
         crate::parse(s)
     }
 
+    /// Check the [`exports`](Self::exports) and [`sections`](Self::sections) for mistakes
+    /// that would make the file fail to link or behave unexpectedly.
+    ///
+    /// Lints are returned in order of appearance and do not have a [`span`](Lint::span),
+    /// use [`ModuleDefinitionFileRef::validate`] in order to get the location in the source.
+    pub fn validate(&self) -> Vec<Lint<'_>> {
+        let exports = self.exports.iter().map(|e| (None, e.to_ref()));
+        let sections = self.sections.iter().map(|s| {
+            let s = SectionRef::new(&s.name, s.read, s.write, s.execute, s.shared);
+            (None, s)
+        });
+
+        let mut lints = Vec::new();
+        crate::validate::validate_inner(exports, sections, &mut |l| lints.push(l));
+        lints
+    }
+
     /// Write the file to a buffer and interpret the buffer as a string.
     ///
     /// It is safe to reuse the same buffer for multiple writes.
     ///
     /// 4 spaces will be used for indentation, and statements will be on separate lines.
     ///
-    /// Errors in parsing [`Sections`](crate::Sections) and [`Exports`](crate::Exports) will be ignored.
+    /// Errors in parsing [`Sections`](crate::Sections), [`Exports`](crate::Exports) and [`Imports`](crate::Imports) will be ignored.
     ///
     /// # Errors
     ///
@@ -66,82 +98,105 @@ impl ModuleDefinitionFile {
     ///
     /// The buffer may contain incomplete data in case of error.
     pub fn write_to_buffer(&self) -> Result<String, core::fmt::Error> {
-        use core::fmt::Write;
-
-        let mut buf = String::new();
-
-        crate::parse_ref::write_file_to_write(
-            &mut buf,
-            self.name.as_ref().map(|a| a.as_ref()),
-            self.is_library,
-            self.base_address,
-            self.heap_reserve,
-            self.heap_commit,
-            self.stack_reserve,
-            self.stack_commit,
-            self.stub.as_ref().map(|a| a.as_ref()),
-            self.major_version,
-            self.minor_version,
-        )?;
-
-        let mut has_header = false;
-        for section in &self.sections {
-            if !has_header {
-                writeln!(buf, "SECTIONS")?;
-                has_header = true;
-            }
-
-            write!(buf, "    {}", section.name)?;
-            if section.read {
-                write!(buf, " READ")?;
-            }
-
-            if section.write {
-                write!(buf, " WRITE")?;
-            }
-
-            if section.execute {
-                write!(buf, " EXECUTE")?;
-            }
+        self.write_to_buffer_with_options(WriteOptions::new())
+    }
 
-            if section.shared {
-                write!(buf, " SHARED")?;
-            }
+    /// Write the file to a string.
+    ///
+    /// Same as [`write_to_buffer`](Self::write_to_buffer), except that the layout is decided by [`WriteOptions`].
+    ///
+    /// # Errors
+    ///
+    /// If formatting fails, a [`core::fmt::Error`] will be returned.
+    pub fn write_to_buffer_with_options(
+        &self,
+        options: WriteOptions,
+    ) -> Result<String, core::fmt::Error> {
+        let mut buf = String::with_capacity(self.written_len_with_options(options));
 
-            writeln!(buf)?;
-        }
+        self.write_to_with_options(&mut buf, options)?;
 
-        has_header = false;
-        for export in &self.exports {
-            if !has_header {
-                writeln!(buf, "EXPORTS")?;
-                has_header = true;
-            }
+        Ok(buf)
+    }
 
-            write!(buf, "    {}", export.name)?;
-            if let Some(internal_name) = &export.internal_name {
-                write!(buf, "={}", internal_name)?;
-            }
+    /// Write the file to any [`core::fmt::Write`], such as a [`Formatter`] or a [`String`].
+    ///
+    /// The layout is the same as [`write_to_buffer`](Self::write_to_buffer).
+    /// Use `IoWriter` in order to write to a `std::io::Write`.
+    ///
+    /// # Errors
+    ///
+    /// If `w` returns an error.
+    pub fn write_to(&self, w: &mut impl core::fmt::Write) -> core::fmt::Result {
+        self.write_to_with_options(w, WriteOptions::new())
+    }
 
-            if let Some(ordinal) = export.ordinal {
-                write!(buf, " @{ordinal}")?;
-                if export.noname {
-                    write!(buf, " NONAME")?;
-                }
-            }
+    /// Write the file to any [`core::fmt::Write`] with the layout decided by [`WriteOptions`].
+    ///
+    /// # Errors
+    ///
+    /// If `w` returns an error.
+    pub fn write_to_with_options(
+        &self,
+        w: &mut impl core::fmt::Write,
+        options: WriteOptions,
+    ) -> core::fmt::Result {
+        let header = Header {
+            name: self.name.as_deref(),
+            is_library: self.is_library,
+            base_address: self.base_address,
+            heap_reserve: self.heap_reserve,
+            heap_commit: self.heap_commit,
+            stack_reserve: self.stack_reserve,
+            stack_commit: self.stack_commit,
+            stub: self.stub.as_deref(),
+            description: self.description.as_deref(),
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+        };
+
+        let sections = self.sections.iter().map(|s| {
+            Ok(SectionRef::new(
+                &s.name, s.read, s.write, s.execute, s.shared,
+            ))
+        });
+        let exports = self.exports.iter().map(|e| Ok(e.to_ref()));
+        let imports = self.imports.iter().map(|i| {
+            Ok(ImportRef::new(
+                i.internal_name.as_deref(),
+                &i.module,
+                i.entry_name.as_deref(),
+                i.ordinal,
+            ))
+        });
 
-            if export.private {
-                write!(buf, " PRIVATE")?;
-            }
+        write_file(w, &header, sections, exports, imports, options, false, true).map_err(
+            |e| match e {
+                WriteError::Format(e) => e,
+                // Items are always valid
+                WriteError::Parse(_) => core::fmt::Error,
+            },
+        )
+    }
 
-            if export.data {
-                write!(buf, " DATA")?;
-            }
+    /// Number of bytes written by [`write_to_buffer`](Self::write_to_buffer).
+    pub fn written_len(&self) -> usize {
+        self.written_len_with_options(WriteOptions::new())
+    }
 
-            writeln!(buf)?;
-        }
+    /// Number of bytes written by [`write_to_buffer_with_options`](Self::write_to_buffer_with_options).
+    pub fn written_len_with_options(&self, options: WriteOptions) -> usize {
+        let mut len = Length(0);
+        // Counting never fails
+        let _ = self.write_to_with_options(&mut len, options);
+        len.0
+    }
+}
 
-        Ok(buf)
+/// Same as [`ModuleDefinitionFile::write_to`].
+impl Display for ModuleDefinitionFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
     }
 }
 
@@ -155,6 +210,9 @@ pub struct Export {
     /// The internal name of the function to export.
     ///
     /// If this is [`None`] the [`name`](Self::name) will be used instead.
+    ///
+    /// Forwarded exports are written as `other_module.exported_name` or `other_module.#ordinal`,
+    /// use [`target`](Self::target) to tell them apart from symbols in the module.
     pub internal_name: Option<String>,
     /// The ordinal associated with the export.
     ///
@@ -166,6 +224,22 @@ pub struct Export {
     pub private: bool,
     /// `The optional keyword DATA specifies that an export is data, not code.`
     pub data: bool,
+    /// Amount of bytes of arguments in the stdcall decoration `name@12`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`](crate::Dialect::Gnu), the [`name`](Self::name) does not include the decoration.
+    pub argument_bytes: Option<u64>,
+    /// Name imported from the module by the symbol in the import library, `name == import_alias`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`](crate::Dialect::Gnu).
+    pub import_alias: Option<String>,
+    /// Legacy `CONSTANT` keyword, which specifies that an export is data that is imported without `__imp_`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`](crate::Dialect::Gnu).
+    pub constant: bool,
+    /// Name the export has in the DLL when it differs from the import library, `name EXPORTAS export_as`.
+    ///
+    /// Used by `lld-link` for ARM64EC and hybrid import libraries.
+    pub export_as: Option<String>,
 }
 
 impl Export {
@@ -185,6 +259,90 @@ impl Export {
             noname,
             private,
             data,
+            argument_bytes: None,
+            import_alias: None,
+            constant: false,
+            export_as: None,
+        }
+    }
+
+    /// Set [`argument_bytes`](Self::argument_bytes).
+    pub const fn with_argument_bytes(mut self, argument_bytes: Option<u64>) -> Self {
+        self.argument_bytes = argument_bytes;
+        self
+    }
+
+    /// Set [`import_alias`](Self::import_alias).
+    pub fn with_import_alias(mut self, import_alias: Option<String>) -> Self {
+        self.import_alias = import_alias;
+        self
+    }
+
+    /// Set [`constant`](Self::constant).
+    pub const fn with_constant(mut self, constant: bool) -> Self {
+        self.constant = constant;
+        self
+    }
+
+    /// Set [`export_as`](Self::export_as).
+    pub fn with_export_as(mut self, export_as: Option<String>) -> Self {
+        self.export_as = export_as;
+        self
+    }
+
+    /// What the export refers to, either a symbol in the module or a function forwarded to another module.
+    pub fn target(&self) -> ExportTarget<'_> {
+        ExportTarget::new(&self.name, self.internal_name.as_deref())
+    }
+
+    pub(crate) fn to_ref(&self) -> ExportRef<'_> {
+        ExportRef::new(
+            &self.name,
+            self.internal_name.as_deref(),
+            self.ordinal,
+            self.noname,
+            self.private,
+            self.data,
+        )
+        .with_argument_bytes(self.argument_bytes)
+        .with_import_alias(self.import_alias.as_deref())
+        .with_constant(self.constant)
+        .with_export_as(self.export_as.as_deref())
+    }
+}
+
+/// Function imported from another module.
+#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Import {
+    /// The name the imported function is known by inside the module.
+    ///
+    /// If this is [`None`] the [`entry_name`](Self::entry_name) will be used instead.
+    pub internal_name: Option<String>,
+    /// The module the function is imported from.
+    pub module: String,
+    /// The exported name of the function in [`module`](Self::module).
+    ///
+    /// Is [`None`] if the function is imported by [`ordinal`](Self::ordinal).
+    pub entry_name: Option<String>,
+    /// The exported ordinal of the function in [`module`](Self::module).
+    ///
+    /// Is [`None`] if the function is imported by [`entry_name`](Self::entry_name).
+    pub ordinal: Option<u64>,
+}
+
+impl Import {
+    /// Create new [`Import`].
+    pub const fn new(
+        internal_name: Option<String>,
+        module: String,
+        entry_name: Option<String>,
+        ordinal: Option<u64>,
+    ) -> Self {
+        Self {
+            internal_name,
+            module,
+            entry_name,
+            ordinal,
         }
     }
 }
@@ -217,29 +375,47 @@ impl Section {
     }
 }
 
-pub(crate) fn parse_inner(s: &str) -> Result<ModuleDefinitionFile, ParseError> {
-    let s = parse_ref(s)?;
-
+/// `on_item_error` decides whether an invalid item is skipped or returned as an error.
+pub(crate) fn parse_inner<'a>(
+    s: ModuleDefinitionFileRef<'a>,
+    on_item_error: &mut impl FnMut(ParseError<'a>) -> Result<(), ParseError<'a>>,
+) -> Result<ModuleDefinitionFile, ParseError<'a>> {
     let mut exports = Vec::new();
     for e in s.exports {
-        let e = e?;
+        let e = match e {
+            Ok(e) => e,
+            Err(err) => {
+                on_item_error(err)?;
+                continue;
+            }
+        };
 
         exports.push(Export {
-            name: e.name.to_string(),
-            internal_name: e.internal_name.map(ToString::to_string),
+            name: unescape(e.name),
+            internal_name: e.internal_name.map(unescape),
             ordinal: e.ordinal,
             noname: e.noname,
             private: e.private,
             data: e.data,
+            argument_bytes: e.argument_bytes,
+            import_alias: e.import_alias.map(unescape),
+            constant: e.constant,
+            export_as: e.export_as.map(unescape),
         });
     }
 
     let mut sections = Vec::new();
     for s in s.sections {
-        let s = s?;
+        let s = match s {
+            Ok(s) => s,
+            Err(err) => {
+                on_item_error(err)?;
+                continue;
+            }
+        };
 
         sections.push(Section {
-            name: s.name.to_string(),
+            name: unescape(s.name),
             read: s.read,
             write: s.write,
             execute: s.execute,
@@ -247,18 +423,43 @@ pub(crate) fn parse_inner(s: &str) -> Result<ModuleDefinitionFile, ParseError> {
         });
     }
 
+    let mut imports = Vec::new();
+    for i in s.imports {
+        let i = match i {
+            Ok(i) => i,
+            Err(err) => {
+                on_item_error(err)?;
+                continue;
+            }
+        };
+
+        imports.push(Import {
+            internal_name: i.internal_name.map(unescape),
+            module: unescape(i.module),
+            entry_name: i.entry_name.map(unescape),
+            ordinal: i.ordinal,
+        });
+    }
+
     Ok(ModuleDefinitionFile {
-        name: s.name.map(ToString::to_string),
+        name: s.name.map(unescape),
         is_library: s.is_library,
         heap_reserve: s.heap_reserve,
         heap_commit: s.heap_commit,
         stack_reserve: s.stack_reserve,
         stack_commit: s.stack_commit,
         base_address: s.base_address,
-        stub: s.stub.map(ToString::to_string),
+        stub: s.stub.map(unescape),
+        description: s.description.map(unescape),
         major_version: s.major_version,
         minor_version: s.minor_version,
         sections,
         exports,
+        imports,
     })
 }
+
+/// Embedded quotes are written as two double quotes (`""`) in the file.
+fn unescape(s: &str) -> String {
+    s.replace("\"\"", "\"")
+}
@@ -1,6 +1,11 @@
-use crate::error::{ParseError, ParseErrorKind};
+use crate::error::{Diagnostic, ParseError, ParseErrorKind, Span, WriteError};
+use crate::options::{Dialect, ParseOptions, WriteOptions};
 use crate::parse_ref;
-use crate::token_iterator::TokenIterator;
+use crate::token_iterator::{
+    find_closing_quote, is_keyword, keyword, TokenIterator, BYTE_ORDER_MARK,
+};
+use crate::write::{write_file, Header, Length};
+use core::fmt::{Display, Formatter};
 
 pub(crate) const COMMENT: &str = ";";
 pub(crate) const ARG_SEPARATOR: &str = ",";
@@ -69,6 +74,9 @@ pub(crate) const RESERVED_WORDS: &[&str] = &[
 ];
 
 /// File representaion that doesn't use `alloc`, but uses iterators instead.
+///
+/// Names and strings are stored without the surrounding quotes,
+/// but embedded quotes are kept as written in the file, two double quotes (`""`).
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct ModuleDefinitionFileRef<'a> {
     /// Name specified by either the `NAME` or `LIBRARY` statements.
@@ -96,6 +104,11 @@ pub struct ModuleDefinitionFileRef<'a> {
     /// `When used in a module definition file that builds a virtual device driver (VxD), allows you to specify a file name that contains an IMAGE_DOS_HEADER structure (defined in WINNT.H) to be used in the virtual device driver (VxD), rather than the default header.`
     pub stub: Option<&'a str>,
 
+    /// `Writes a string into an .rdata section.`
+    ///
+    /// Embedded quotes are kept as written in the file, two double quotes (`""`).
+    pub description: Option<&'a str>,
+
     /// `Tells LINK to put a number in the header of the .exe file or DLL. The default is version 0.`
     pub major_version: Option<u16>,
     /// `Tells LINK to put a number in the header of the .exe file or DLL. The default is version 0.`
@@ -105,6 +118,8 @@ pub struct ModuleDefinitionFileRef<'a> {
     pub sections: Sections<'a>,
     /// `Introduces a section of one or more export definitions that specify the exported names or ordinals of functions or data.`
     pub exports: Exports<'a>,
+    /// Legacy statement that introduces a section of one or more functions imported from other modules.
+    pub imports: Imports<'a>,
 }
 
 impl<'a> ModuleDefinitionFileRef<'a> {
@@ -117,7 +132,7 @@ impl<'a> ModuleDefinitionFileRef<'a> {
         parse_ref(file)
     }
 
-    pub(crate) fn inner_new(file: &'a str) -> Self {
+    pub(crate) fn inner_new(file: &'a str, options: ParseOptions) -> Self {
         Self {
             name: None,
             is_library: None,
@@ -127,20 +142,50 @@ impl<'a> ModuleDefinitionFileRef<'a> {
             stack_commit: None,
             base_address: None,
             stub: None,
+            description: None,
             major_version: None,
             minor_version: None,
-            sections: Sections::new(file),
-            exports: Exports::new(file),
+            sections: Sections::with_options(file, options),
+            exports: Exports::with_options(file, options),
+            imports: Imports::with_options(file, options),
         }
     }
 
+    /// Iterate over all errors in [`Sections`], [`Exports`] and [`Imports`]
+    /// in the order they appear in the file.
+    ///
+    /// [`parse_ref`](crate::parse_ref) only reports errors in top level statements,
+    /// since the items are parsed lazily.
+    pub fn errors(&self) -> impl Iterator<Item = ParseError<'a>> {
+        let mut sections = self.sections.filter_map(Result::err).peekable();
+        let mut exports = self.exports.filter_map(Result::err).peekable();
+        let mut imports = self.imports.filter_map(Result::err).peekable();
+
+        // Every kind is already in file order, so picking the earliest of the three keeps the order.
+        core::iter::from_fn(move || {
+            let start = |e: Option<&ParseError<'_>>| e.map_or(usize::MAX, |e| e.span.start);
+            let section = start(sections.peek());
+            let export = start(exports.peek());
+            let import = start(imports.peek());
+
+            if section <= export && section <= import {
+                sections.next()
+            } else if export <= import {
+                exports.next()
+            } else {
+                imports.next()
+            }
+        })
+    }
+
     /// Write the file to a buffer and interpret the buffer as a string.
     ///
     /// It is safe to reuse the same buffer for multiple writes.
     ///
     /// 4 spaces will be used for indentation, and statements will be on separate lines.
     ///
-    /// Errors in parsing [`Sections`] and [`Exports`] will be ignored.
+    /// Errors in parsing [`Sections`], [`Exports`] and [`Imports`] will be ignored,
+    /// use [`write_to_buffer_strict`](Self::write_to_buffer_strict) to have them returned.
     ///
     /// # Errors
     ///
@@ -152,162 +197,144 @@ impl<'a> ModuleDefinitionFileRef<'a> {
         &self,
         buf: &'buf mut [u8],
     ) -> Result<Result<&'buf str, core::str::Utf8Error>, core::fmt::Error> {
-        use core::fmt::Write;
+        self.write_to_buffer_with_options(buf, WriteOptions::new())
+    }
 
+    /// Write the file to a buffer and interpret the buffer as a string.
+    ///
+    /// Same as [`write_to_buffer`](Self::write_to_buffer),
+    /// except that errors in parsing [`Sections`], [`Exports`] and [`Imports`] are returned instead of ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the buffer isn't of sufficient size, a [`WriteError::Format`] will be returned.
+    /// If an item can not be parsed, a [`WriteError::Parse`] with the first error will be returned.
+    /// If the buffer isn't valid UTF-8, a [`core::str::Utf8Error`] will be returned.
+    ///
+    /// The buffer may contain incomplete data in case of error.
+    pub fn write_to_buffer_strict<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<Result<&'buf str, core::str::Utf8Error>, WriteError<'a>> {
         let mut buf = Wrapper { buf, offset: 0 };
 
-        write_file_to_write(
-            &mut buf,
-            self.name,
-            self.is_library,
-            self.base_address,
-            self.heap_reserve,
-            self.heap_commit,
-            self.stack_reserve,
-            self.stack_commit,
-            self.stub,
-            self.major_version,
-            self.minor_version,
-        )?;
-
-        let mut has_header = false;
-        let sections = self.sections;
-        for section in sections {
-            let Ok(section) = section else {
-                continue;
-            };
-
-            if !has_header {
-                writeln!(buf, "SECTIONS")?;
-                has_header = true;
-            }
+        self.write_to_write(&mut buf, WriteOptions::new(), true)?;
 
-            write!(buf, "    {}", section.name)?;
-            if section.read {
-                write!(buf, " READ")?;
-            }
-
-            if section.write {
-                write!(buf, " WRITE")?;
-            }
-
-            if section.execute {
-                write!(buf, " EXECUTE")?;
-            }
-
-            if section.shared {
-                write!(buf, " SHARED")?;
-            }
-
-            writeln!(buf)?;
-        }
-
-        has_header = false;
-        let exports = self.exports;
-        for export in exports {
-            let Ok(export) = export else {
-                continue;
-            };
-
-            if !has_header {
-                writeln!(buf, "EXPORTS")?;
-                has_header = true;
-            }
-
-            write!(buf, "    {}", export.name)?;
-            if let Some(internal_name) = export.internal_name {
-                write!(buf, "={}", internal_name)?;
-            }
-
-            if let Some(ordinal) = export.ordinal {
-                write!(buf, " @{ordinal}")?;
-                if export.noname {
-                    write!(buf, " NONAME")?;
-                }
-            }
-
-            if export.private {
-                write!(buf, " PRIVATE")?;
-            }
+        Ok(core::str::from_utf8(&buf.buf[..buf.offset]))
+    }
 
-            if export.data {
-                write!(buf, " DATA")?;
-            }
+    /// Write the file to a buffer and interpret the buffer as a string.
+    ///
+    /// Same as [`write_to_buffer`](Self::write_to_buffer), except that the layout is decided by [`WriteOptions`].
+    ///
+    /// # Errors
+    ///
+    /// If the buffer isn't of sufficient size, a [`core::fmt::Result`] will be returned.
+    /// If the buffer isn't valid UTF-8, a [`core::str::Utf8Error`] will be returned.
+    ///
+    /// The buffer may contain incomplete data in case of error.
+    pub fn write_to_buffer_with_options<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+        options: WriteOptions,
+    ) -> Result<Result<&'buf str, core::str::Utf8Error>, core::fmt::Error> {
+        let mut buf = Wrapper { buf, offset: 0 };
 
-            writeln!(buf)?;
-        }
+        self.write_to_with_options(&mut buf, options)?;
 
         Ok(core::str::from_utf8(&buf.buf[..buf.offset]))
     }
-}
-
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn write_file_to_write(
-    buf: &mut impl core::fmt::Write,
-    name: Option<&str>,
-    is_library: Option<bool>,
-    base_address: Option<u64>,
-    heap_reserve: Option<u64>,
-    heap_commit: Option<u64>,
-    stack_reserve: Option<u64>,
-    stack_commit: Option<u64>,
-    stub: Option<&str>,
-    major_version: Option<u16>,
-    minor_version: Option<u16>,
-) -> Result<(), core::fmt::Error> {
-    if let Some(name) = name {
-        let quote = if needs_quotes(name) { "\"" } else { "" };
-
-        if let Some(is_lib) = is_library {
-            if is_lib {
-                write!(buf, "LIBRARY {quote}{}{quote}", name)?;
-            } else {
-                write!(buf, "NAME {quote}{}{quote}", name)?;
-            }
-        }
 
-        if let Some(base) = base_address {
-            write!(buf, " BASE={base:#X}")?;
-        }
+    /// Write the file to any [`core::fmt::Write`], such as a [`Formatter`] or a `String`.
+    ///
+    /// The layout is the same as [`write_to_buffer`](Self::write_to_buffer),
+    /// and errors in parsing [`Sections`], [`Exports`] and [`Imports`] will be ignored.
+    /// Use `IoWriter` in order to write to a `std::io::Write`.
+    ///
+    /// # Errors
+    ///
+    /// If `w` returns an error.
+    pub fn write_to(&self, w: &mut impl core::fmt::Write) -> core::fmt::Result {
+        self.write_to_with_options(w, WriteOptions::new())
+    }
 
-        writeln!(buf)?;
+    /// Write the file to any [`core::fmt::Write`] with the layout decided by [`WriteOptions`].
+    ///
+    /// # Errors
+    ///
+    /// If `w` returns an error.
+    pub fn write_to_with_options(
+        &self,
+        w: &mut impl core::fmt::Write,
+        options: WriteOptions,
+    ) -> core::fmt::Result {
+        self.write_to_write(w, options, false).map_err(|e| match e {
+            WriteError::Format(e) => e,
+            // Not returned when not strict
+            WriteError::Parse(_) => core::fmt::Error,
+        })
     }
 
-    if let Some(reserve) = heap_reserve {
-        if let Some(commit) = heap_commit {
-            writeln!(buf, "HEAPSIZE {reserve:#X},{commit:#X}")?;
-        } else {
-            writeln!(buf, "HEAPSIZE {reserve:#X}")?;
-        }
+    /// Number of bytes written by [`write_to_buffer`](Self::write_to_buffer).
+    ///
+    /// ```rust
+    /// let file = msvc_def::parse_ref("LIBRARY mylib\nEXPORTS\n    myfunc @1\n").unwrap();
+    ///
+    /// let mut buf = [0_u8; 64];
+    /// let len = file.written_len();
+    /// assert!(file.write_to_buffer(&mut buf[..len]).is_ok());
+    /// assert!(file.write_to_buffer(&mut buf[..len - 1]).is_err());
+    /// ```
+    pub fn written_len(&self) -> usize {
+        self.written_len_with_options(WriteOptions::new())
     }
 
-    if let Some(reserve) = stack_reserve {
-        if let Some(commit) = stack_commit {
-            writeln!(buf, "STACKSIZE {reserve:#X},{commit:#X}")?;
-        } else {
-            writeln!(buf, "STACKSIZE {reserve:#X}")?;
-        }
+    /// Number of bytes written by [`write_to_buffer_with_options`](Self::write_to_buffer_with_options).
+    pub fn written_len_with_options(&self, options: WriteOptions) -> usize {
+        let mut len = Length(0);
+        // Counting never fails
+        let _ = self.write_to_with_options(&mut len, options);
+        len.0
     }
 
-    if let Some(stub) = stub {
-        let quote = if crate::parse_ref::needs_quotes(stub) {
-            "\""
-        } else {
-            ""
+    fn write_to_write(
+        &self,
+        buf: &mut impl core::fmt::Write,
+        options: WriteOptions,
+        strict: bool,
+    ) -> Result<(), WriteError<'a>> {
+        let header = Header {
+            name: self.name,
+            is_library: self.is_library,
+            base_address: self.base_address,
+            heap_reserve: self.heap_reserve,
+            heap_commit: self.heap_commit,
+            stack_reserve: self.stack_reserve,
+            stack_commit: self.stack_commit,
+            stub: self.stub,
+            description: self.description,
+            major_version: self.major_version,
+            minor_version: self.minor_version,
         };
 
-        writeln!(buf, "STUB:{quote}{}{quote}", stub)?;
+        write_file(
+            buf,
+            &header,
+            self.sections,
+            self.exports,
+            self.imports,
+            options,
+            strict,
+            false,
+        )
     }
+}
 
-    if let Some(major_version) = major_version {
-        if let Some(minor_version) = minor_version {
-            writeln!(buf, "VERSION {major_version}.{minor_version}")?;
-        } else {
-            writeln!(buf, "VERSION {major_version}")?;
-        }
+/// Same as [`ModuleDefinitionFileRef::write_to`].
+impl Display for ModuleDefinitionFileRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write_to(f)
     }
-
-    Ok(())
 }
 
 /// Iterator over [`ExportRef`]s.
@@ -320,16 +347,58 @@ impl<'a> Exports<'a> {
     /// Create a new iterator from a `str`.
     /// This should be the same as is passed to [`ModuleDefinitionFileRef::new`].
     pub fn new(inner: &'a str) -> Self {
-        let mut it = TokenIterator::new(inner);
+        Self::with_options(inner, ParseOptions::new())
+    }
+
+    /// Create a new iterator from a `str` with custom [`ParseOptions`].
+    /// This should be the same as is passed to [`parse_ref_with_options`](crate::parse_ref_with_options).
+    pub fn with_options(inner: &'a str, options: ParseOptions) -> Self {
+        let mut it = TokenIterator::new(inner, options);
 
         while let Some(token) = it.eat_token() {
-            if token == "EXPORTS" {
+            if it.keyword(token) == "EXPORTS" {
                 break;
             }
         }
 
         Self { it }
     }
+
+    /// Skip the invalid token and the remaining attributes so they are not read as the next export.
+    fn skip_invalid_export(&mut self, e: ParseError<'a>) -> ParseError<'a> {
+        let _invalid = self.it.eat_token();
+
+        while let Some(token) = self.it.peek_token() {
+            if !EXPORT_ATTRIBUTES.contains(&self.it.keyword(token)) && !token.starts_with('@') {
+                break;
+            }
+
+            self.it.eat_token();
+        }
+
+        self.skip_to_next_export();
+
+        e
+    }
+
+    /// Same as [`next`](Iterator::next), but also returns the span of the export name.
+    pub(crate) fn next_spanned(&mut self) -> Option<(Span, Result<ExportRef<'a>, ParseError<'a>>)> {
+        let name = self.it.peek_token()?;
+        let span = token_span(self.it.offset, name);
+
+        self.next().map(|e| (span, e))
+    }
+
+    fn skip_to_next_export(&mut self) {
+        // Next token isn't part of this sections
+        if self.it.next_token_is_keyword() {
+            while let Some(token) = self.it.eat_token() {
+                if self.it.keyword(token) == "EXPORTS" {
+                    break;
+                }
+            }
+        }
+    }
 }
 
 impl<'a> Iterator for Exports<'a> {
@@ -341,29 +410,78 @@ impl<'a> Iterator for Exports<'a> {
         let mut noname = false;
         let mut private = false;
         let mut data = false;
+        let mut argument_bytes = None;
+        let mut import_alias = None;
+        let mut constant = false;
+        let mut export_as = None;
+        let gnu = self.it.dialect == Dialect::Gnu;
 
         if let Some(name) = self.it.eat_token() {
+            let mut name = strip_ident(name);
+            if gnu {
+                if let Some((undecorated, bytes)) = split_stdcall_decoration(name) {
+                    name = undecorated;
+                    argument_bytes = Some(bytes);
+                }
+            }
+
             while let Some(token) = self.it.peek_token() {
-                match token {
+                match self.it.keyword(token) {
                     "=" => {
                         let Some(_equals) = self.it.eat_token() else {
                             return Some(Err(ParseError::missing_arg("EXPORTS", self.it.offset)));
                         };
 
+                        if gnu && self.it.next_token_is("=") {
+                            let _equals = self.it.eat_token().unwrap();
+
+                            let Some(alias) = self.it.peek_token() else {
+                                return Some(Err(ParseError::missing_arg(
+                                    "EXPORTS",
+                                    self.it.offset,
+                                )));
+                            };
+
+                            import_alias = Some(strip_ident(alias));
+                            self.it.eat_token().unwrap();
+                            continue;
+                        }
+
                         let Some(internal_name2) = self.it.peek_token() else {
                             return Some(Err(ParseError::missing_arg("EXPORTS", self.it.offset)));
                         };
-                        internal_name = Some(internal_name2);
+
+                        if let Some((module, ordinal)) = internal_name2.rsplit_once('.') {
+                            if let Some(ordinal) = ordinal.strip_prefix('#') {
+                                let offset = self.it.offset + module.len() + 2;
+                                if let Err(e) = parse_number(ordinal, offset) {
+                                    return Some(Err(self.skip_invalid_export(e)));
+                                }
+                            }
+                        }
+
+                        internal_name = Some(strip_ident(internal_name2));
                     }
                     "NONAME" => noname = true,
                     "PRIVATE" => private = true,
                     "DATA" => data = true,
-                    ord if ord.starts_with('@') => {
-                        let ord = ord.trim_start_matches('@');
+                    "CONSTANT" if gnu => constant = true,
+                    "EXPORTAS" => {
+                        let _export_as = self.it.eat_token().unwrap();
+
+                        let Some(name) = self.it.peek_token() else {
+                            return Some(Err(ParseError::missing_arg("EXPORTAS", self.it.offset)));
+                        };
 
-                        let ord = match parse_number(ord, self.it.offset) {
+                        export_as = Some(strip_ident(name));
+                    }
+                    token if token.starts_with('@') => {
+                        let ord = token.trim_start_matches('@');
+                        let offset = self.it.offset + token.len() - ord.len();
+
+                        let ord = match parse_number(ord, offset) {
                             Ok(o) => o,
-                            Err(e) => return Some(Err(e)),
+                            Err(e) => return Some(Err(self.skip_invalid_export(e))),
                         };
 
                         ordinal = Some(ord);
@@ -374,23 +492,14 @@ impl<'a> Iterator for Exports<'a> {
                 self.it.eat_token().unwrap();
             }
 
-            // Next token isn't part of this sections
-            if self.it.next_token_is_keyword() {
-                while let Some(token) = self.it.eat_token() {
-                    if token == "EXPORTS" {
-                        break;
-                    }
-                }
-            }
+            self.skip_to_next_export();
 
-            return Some(Ok(ExportRef::new(
-                name,
-                internal_name,
-                ordinal,
-                noname,
-                private,
-                data,
-            )));
+            let export = ExportRef::new(name, internal_name, ordinal, noname, private, data)
+                .with_argument_bytes(argument_bytes)
+                .with_import_alias(import_alias)
+                .with_constant(constant)
+                .with_export_as(export_as);
+            return Some(Ok(export));
         }
 
         None
@@ -407,6 +516,9 @@ pub struct ExportRef<'a> {
     /// The internal name of the function to export.
     ///
     /// If this is [`None`] the [`name`](Self::name) will be used instead.
+    ///
+    /// Forwarded exports are written as `other_module.exported_name` or `other_module.#ordinal`,
+    /// use [`target`](Self::target) to tell them apart from symbols in the module.
     pub internal_name: Option<&'a str>,
     /// The ordinal associated with the export.
     ///
@@ -418,6 +530,22 @@ pub struct ExportRef<'a> {
     pub private: bool,
     /// `The optional keyword DATA specifies that an export is data, not code.`
     pub data: bool,
+    /// Amount of bytes of arguments in the stdcall decoration `name@12`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`], the [`name`](Self::name) does not include the decoration.
+    pub argument_bytes: Option<u64>,
+    /// Name imported from the module by the symbol in the import library, `name == import_alias`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`].
+    pub import_alias: Option<&'a str>,
+    /// Legacy `CONSTANT` keyword, which specifies that an export is data that is imported without `__imp_`.
+    ///
+    /// Only parsed with [`Dialect::Gnu`].
+    pub constant: bool,
+    /// Name the export has in the DLL when it differs from the import library, `name EXPORTAS export_as`.
+    ///
+    /// Used by `lld-link` for ARM64EC and hybrid import libraries.
+    pub export_as: Option<&'a str>,
 }
 
 impl<'a> ExportRef<'a> {
@@ -437,6 +565,262 @@ impl<'a> ExportRef<'a> {
             noname,
             private,
             data,
+            argument_bytes: None,
+            import_alias: None,
+            constant: false,
+            export_as: None,
+        }
+    }
+
+    /// Set [`argument_bytes`](Self::argument_bytes).
+    pub const fn with_argument_bytes(mut self, argument_bytes: Option<u64>) -> Self {
+        self.argument_bytes = argument_bytes;
+        self
+    }
+
+    /// Set [`import_alias`](Self::import_alias).
+    pub const fn with_import_alias(mut self, import_alias: Option<&'a str>) -> Self {
+        self.import_alias = import_alias;
+        self
+    }
+
+    /// Set [`constant`](Self::constant).
+    pub const fn with_constant(mut self, constant: bool) -> Self {
+        self.constant = constant;
+        self
+    }
+
+    /// Set [`export_as`](Self::export_as).
+    pub const fn with_export_as(mut self, export_as: Option<&'a str>) -> Self {
+        self.export_as = export_as;
+        self
+    }
+
+    /// What the export refers to, either a symbol in the module or a function forwarded to another module.
+    pub fn target(&self) -> ExportTarget<'a> {
+        ExportTarget::new(self.name, self.internal_name)
+    }
+}
+
+/// What an export refers to.
+///
+/// Created from the [`internal_name`](ExportRef::internal_name) of an export,
+/// or the [`name`](ExportRef::name) if there is no internal name.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ExportTarget<'a> {
+    /// Symbol defined in the module itself.
+    Symbol(&'a str),
+    /// `entryname=other_module.exported_name`
+    ///
+    /// Forwarded to a function exported by name from another module.
+    ForwardName {
+        /// Module without file extension.
+        module: &'a str,
+        /// Name of the export in [`module`](Self::ForwardName::module).
+        name: &'a str,
+    },
+    /// `entryname=other_module.#ordinal`
+    ///
+    /// Forwarded to a function exported by ordinal from another module.
+    ForwardOrdinal {
+        /// Module without file extension.
+        module: &'a str,
+        /// Ordinal of the export in [`module`](Self::ForwardOrdinal::module).
+        ordinal: u64,
+    },
+}
+
+impl<'a> ExportTarget<'a> {
+    pub(crate) fn new(name: &'a str, internal_name: Option<&'a str>) -> Self {
+        let Some(internal_name) = internal_name else {
+            return Self::Symbol(name);
+        };
+
+        // Module names can contain periods, entry names can not
+        let Some((module, name)) = internal_name.rsplit_once('.') else {
+            return Self::Symbol(internal_name);
+        };
+
+        if let Some(ordinal) = name.strip_prefix('#') {
+            if let Ok(ordinal) = parse_number(ordinal, 0) {
+                return Self::ForwardOrdinal { module, ordinal };
+            }
+        }
+
+        Self::ForwardName { module, name }
+    }
+
+    /// Returns [`true`] if the export is forwarded to another module.
+    pub const fn is_forward(&self) -> bool {
+        !matches!(self, Self::Symbol(_))
+    }
+
+    /// The module the export is forwarded to, if any.
+    pub const fn module(&self) -> Option<&'a str> {
+        match self {
+            Self::Symbol(_) => None,
+            Self::ForwardName { module, .. } | Self::ForwardOrdinal { module, .. } => Some(module),
+        }
+    }
+}
+
+impl Display for ExportTarget<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExportTarget::Symbol(name) => write!(f, "{name}"),
+            ExportTarget::ForwardName { module, name } => write!(f, "{module}.{name}"),
+            ExportTarget::ForwardOrdinal { module, ordinal } => write!(f, "{module}.#{ordinal}"),
+        }
+    }
+}
+
+/// Iterator over [`ImportRef`]s.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Imports<'a> {
+    it: TokenIterator<'a>,
+}
+
+impl<'a> Imports<'a> {
+    /// Create a new iterator from a `str`.
+    /// This should be the same as is passed to [`ModuleDefinitionFileRef::new`].
+    pub fn new(inner: &'a str) -> Self {
+        Self::with_options(inner, ParseOptions::new())
+    }
+
+    /// Create a new iterator from a `str` with custom [`ParseOptions`].
+    /// This should be the same as is passed to [`parse_ref_with_options`](crate::parse_ref_with_options).
+    pub fn with_options(inner: &'a str, options: ParseOptions) -> Self {
+        let mut it = TokenIterator::new(inner, options);
+
+        while let Some(token) = it.eat_token() {
+            if it.keyword(token) == "IMPORTS" {
+                break;
+            }
+        }
+
+        Self { it }
+    }
+
+    /// Skip the remaining tokens of an invalid import so they are not read as the next import.
+    fn skip_invalid_import(&mut self, e: ParseError<'a>) -> ParseError<'a> {
+        self.skip_to_next_import();
+
+        e
+    }
+
+    fn skip_to_next_import(&mut self) {
+        // Next token isn't part of this section
+        if self.it.next_token_is_keyword() {
+            while let Some(token) = self.it.eat_token() {
+                if self.it.keyword(token) == "IMPORTS" {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Imports<'a> {
+    type Item = Result<ImportRef<'a>, ParseError<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.it.peek_token()?;
+
+        // Quoted names are separate tokens from what follows them, so names are read like targets
+        let first_offset = self.it.offset;
+        let first = eat_import_target(&mut self.it);
+
+        let (internal_name, target, target_offset) =
+            if !first.is_empty() && self.it.next_token_is("=") {
+                // The rest of the file can be a comment
+                if self.it.eat_token().is_none() || self.it.peek_token().is_none() {
+                    return Some(Err(ParseError::missing_arg("IMPORTS", self.it.offset)));
+                }
+
+                let target_offset = self.it.offset;
+                let target = eat_import_target(&mut self.it);
+
+                (Some(strip_quotes(first)), target, target_offset)
+            } else {
+                (None, first, first_offset)
+            };
+
+        // Module names can contain periods, entry names can not
+        let Some((module, entry)) = split_import_target(target) else {
+            if target.is_empty() {
+                // Skip the token that is not a target so that iteration ends
+                let _invalid = self.it.eat_token();
+            }
+
+            return Some(Err(self.skip_invalid_import(ParseError::new(
+                ParseErrorKind::MissingModuleFor("IMPORTS"),
+                target_offset,
+            ))));
+        };
+
+        let entry_offset = target_offset + module.len() + 1;
+        let (entry_name, ordinal) = if entry.starts_with(DOUBLE_QUOTE) {
+            (Some(strip_quotes(entry)), None)
+        } else if let Some(ordinal) = entry.strip_prefix('@') {
+            match parse_number(ordinal, entry_offset + 1) {
+                Ok(o) => (None, Some(o)),
+                Err(e) => return Some(Err(self.skip_invalid_import(e))),
+            }
+        } else if entry.starts_with(|a: char| a.is_ascii_digit()) {
+            match parse_number(entry, entry_offset) {
+                Ok(o) => (None, Some(o)),
+                Err(e) => return Some(Err(self.skip_invalid_import(e))),
+            }
+        } else {
+            (Some(entry), None)
+        };
+        let module = strip_quotes(module);
+
+        self.skip_to_next_import();
+
+        Some(Ok(ImportRef::new(
+            internal_name,
+            module,
+            entry_name,
+            ordinal,
+        )))
+    }
+}
+
+/// `[A] section of one or more functions imported from other modules.`
+///
+/// Only supported by legacy versions of LINK.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct ImportRef<'a> {
+    /// The name the imported function is known by inside the module.
+    ///
+    /// If this is [`None`] the [`entry_name`](Self::entry_name) will be used instead.
+    pub internal_name: Option<&'a str>,
+    /// The module the function is imported from.
+    pub module: &'a str,
+    /// The exported name of the function in [`module`](Self::module).
+    ///
+    /// Is [`None`] if the function is imported by [`ordinal`](Self::ordinal).
+    pub entry_name: Option<&'a str>,
+    /// The exported ordinal of the function in [`module`](Self::module).
+    ///
+    /// Is [`None`] if the function is imported by [`entry_name`](Self::entry_name).
+    pub ordinal: Option<u64>,
+}
+
+impl<'a> ImportRef<'a> {
+    /// Create a new import item.
+    pub const fn new(
+        internal_name: Option<&'a str>,
+        module: &'a str,
+        entry_name: Option<&'a str>,
+        ordinal: Option<u64>,
+    ) -> Self {
+        Self {
+            internal_name,
+            module,
+            entry_name,
+            ordinal,
         }
     }
 }
@@ -451,16 +835,32 @@ impl<'a> Sections<'a> {
     /// Create a new iterator from a `str`.
     /// This should be the same as is passed to [`ModuleDefinitionFileRef::new`].
     pub fn new(inner: &'a str) -> Self {
-        let mut it = TokenIterator::new(inner);
+        Self::with_options(inner, ParseOptions::new())
+    }
+
+    /// Create a new iterator from a `str` with custom [`ParseOptions`].
+    /// This should be the same as is passed to [`parse_ref_with_options`](crate::parse_ref_with_options).
+    pub fn with_options(inner: &'a str, options: ParseOptions) -> Self {
+        let mut it = TokenIterator::new(inner, options);
 
         while let Some(token) = it.eat_token() {
-            if token == "SECTIONS" {
+            if it.keyword(token) == "SECTIONS" {
                 break;
             }
         }
 
         Self { it }
     }
+
+    /// Same as [`next`](Iterator::next), but also returns the span of the section name.
+    pub(crate) fn next_spanned(
+        &mut self,
+    ) -> Option<(Span, Result<SectionRef<'a>, ParseError<'a>>)> {
+        let name = self.it.peek_token()?;
+        let span = token_span(self.it.offset, name);
+
+        self.next().map(|s| (span, s))
+    }
 }
 
 impl<'a> Iterator for Sections<'a> {
@@ -474,7 +874,7 @@ impl<'a> Iterator for Sections<'a> {
 
         if let Some(name) = self.it.eat_token() {
             while let Some(token) = self.it.peek_token() {
-                match token {
+                match self.it.keyword(token) {
                     "READ" => read = true,
                     "WRITE" => write = true,
                     "EXECUTE" => execute = true,
@@ -500,13 +900,19 @@ impl<'a> Iterator for Sections<'a> {
             // Next token isn't part of this sections
             if self.it.next_token_is_keyword() {
                 while let Some(token) = self.it.eat_token() {
-                    if token == "SECTIONS" {
+                    if self.it.keyword(token) == "SECTIONS" {
                         break;
                     }
                 }
             }
 
-            return Some(Ok(SectionRef::new(name, read, write, execute, shared)));
+            return Some(Ok(SectionRef::new(
+                strip_ident(name),
+                read,
+                write,
+                execute,
+                shared,
+            )));
         }
 
         None
@@ -541,28 +947,78 @@ impl<'a> SectionRef<'a> {
     }
 }
 
-pub fn parse_ref_inner(s: &str) -> Result<ModuleDefinitionFileRef<'_>, ParseError<'_>> {
-    let mut it = TokenIterator::new(s);
+/// Statements that are supported, as opposed to legacy statements that are only reserved.
+pub(crate) const STATEMENTS: &[&str] = &[
+    "DESCRIPTION",
+    "EXPORTS",
+    "HEAPSIZE",
+    "IMPORTS",
+    "LIBRARY",
+    "NAME",
+    "SECTIONS",
+    "STACKSIZE",
+    "STUB",
+    "VERSION",
+];
+
+/// Keywords that can follow an export without ending the `EXPORTS` statement.
+pub(crate) const EXPORT_ATTRIBUTES: &[&str] = &["NONAME", "PRIVATE", "DATA"];
+
+/// Keywords that can follow a section without ending the `SECTIONS` statement.
+pub(crate) const SECTION_ATTRIBUTES: &[&str] = &["READ", "WRITE", "EXECUTE", "SHARED"];
+
+pub fn parse_ref_inner<'a>(
+    s: &'a str,
+    options: ParseOptions,
+    recover: bool,
+    diagnostics: &mut impl FnMut(Diagnostic<'a>),
+) -> Result<ModuleDefinitionFileRef<'a>, ParseError<'a>> {
+    let mut it = TokenIterator::new(s, options);
+
+    let mut file = ModuleDefinitionFileRef::inner_new(s, options);
+    loop {
+        let offset = it.offset;
+        let Some(token) = it.eat_token() else {
+            break;
+        };
+
+        if let Err(e) = parser_inner(s, token, offset, &mut it, &mut file, options, diagnostics) {
+            if !recover {
+                return Err(e);
+            }
+
+            diagnostics(Diagnostic::error(e));
+
+            // Resynchronize at the next statement or line
+            while let Some(token) = it.peek_token() {
+                if is_line_start(s, it.offset) || STATEMENTS.contains(&it.keyword(token)) {
+                    break;
+                }
 
-    let mut file = ModuleDefinitionFileRef::inner_new(s);
-    while let Some(token) = it.eat_token() {
-        parser_inner(token, &mut it, &mut file)?;
+                it.eat_token();
+            }
+        }
     }
 
     Ok(file)
 }
 
 fn parser_inner<'a>(
+    s: &'a str,
     token: &'a str,
+    offset: usize,
     it: &mut TokenIterator<'a>,
     file: &mut ModuleDefinitionFileRef<'a>,
+    options: ParseOptions,
+    diagnostics: &mut impl FnMut(Diagnostic<'a>),
 ) -> Result<(), ParseError<'a>> {
-    match token {
+    let keyword = it.keyword(token);
+    match keyword {
         "NAME" | "LIBRARY" => {
-            file.is_library = Some(token == "LIBRARY");
+            file.is_library = Some(keyword == "LIBRARY");
 
             if let Some(next_token) = it.peek_token() {
-                if !next_token.starts_with("BASE") && !RESERVED_WORDS.contains(&next_token) {
+                if !RESERVED_WORDS.contains(&it.keyword(next_token)) {
                     let name = it.eat_token().unwrap();
 
                     file.name = Some(strip_ident(name));
@@ -578,11 +1034,12 @@ fn parser_inner<'a>(
                         ));
                     };
 
+                    let base_offset = it.offset;
                     let Some(base) = it.eat_token() else {
                         return Err(ParseError::missing_arg("BASE", it.offset));
                     };
 
-                    let base = parse_number(base, it.offset)?;
+                    let base = parse_number(base, base_offset)?;
                     file.base_address = Some(base);
                 }
             }
@@ -613,36 +1070,63 @@ fn parser_inner<'a>(
 
             file.stub = Some(strip_ident(stub));
         }
+        "DESCRIPTION" => {
+            let Some(description) = it.eat_token() else {
+                return Err(ParseError::missing_arg("DESCRIPTION", it.offset));
+            };
+
+            file.description = Some(strip_ident(description));
+        }
         "VERSION" => {
+            let major_offset = it.offset;
             let Some(major) = it.eat_token() else {
                 return Err(ParseError::missing_arg("VERSION", it.offset));
             };
 
-            let major = parse_u16(major, it.offset - major.len())?;
+            let major = parse_u16(major, major_offset)?;
             file.major_version = Some(major);
 
             if it.next_token_is(".") {
                 let _period = it.eat_token().unwrap();
 
+                let minor_offset = it.offset;
                 let Some(minor) = it.eat_token() else {
                     return Err(ParseError::missing_arg("VERSION", it.offset));
                 };
-                let minor = parse_u16(minor, it.offset)?;
+                let minor = parse_u16(minor, minor_offset)?;
 
                 file.minor_version = Some(minor);
             }
         }
-        "SECTIONS" => {
-            while let Some(token) = it.peek_token() {
-                if RESERVED_WORDS.contains(&token) {
-                    break;
-                }
+        "SECTIONS" => skip_items(it, SECTION_ATTRIBUTES),
+        "EXPORTS" => skip_items(it, EXPORT_ATTRIBUTES),
+        "IMPORTS" => skip_items(it, &[]),
+
+        _ => {
+            let unknown_statement = is_line_start(s, offset) || RESERVED_WORDS.contains(&keyword);
+            let kind = if unknown_statement {
+                ParseErrorKind::UnknownStatement(token)
+            } else {
+                ParseErrorKind::UnexpectedToken(token)
+            };
+            let e = ParseError::with_span(kind, Span::new(offset, offset + token.len()));
 
-                it.eat_token();
+            if options.is_strict() {
+                return Err(e);
             }
-        }
+            diagnostics(Diagnostic::warning(e));
 
-        _ => {}
+            if unknown_statement {
+                // Skip the arguments of the statement in order to only warn once
+                while let Some(token) = it.peek_token() {
+                    if is_line_start(s, it.offset) || STATEMENTS.contains(&it.keyword(token)) {
+                        break;
+                    }
+
+                    it.eat_token();
+                }
+            }
+        }
     }
 
     Ok(())
@@ -663,6 +1147,8 @@ fn parse_double_arg<'a>(
 
     let commit = if it.next_token_is(ARG_SEPARATOR) {
         let _comma = it.eat_token().unwrap();
+
+        let commit_offset = it.offset;
         let Some(commit) = it.eat_token() else {
             return Err(ParseError::new(
                 ParseErrorKind::MissingArgumentAfterCommaFor(keyword),
@@ -670,13 +1156,7 @@ fn parse_double_arg<'a>(
             ));
         };
 
-        Some(commit)
-    } else {
-        None
-    };
-
-    let commit = if let Some(commit) = commit {
-        Some(parse_number(commit, it.offset)?)
+        Some(parse_number(commit, commit_offset)?)
     } else {
         None
     };
@@ -716,8 +1196,104 @@ fn parse_number(s: &str, offset: usize) -> Result<u64, ParseError<'_>> {
     })
 }
 
+/// Items are parsed lazily by their own iterators.
+fn skip_items(it: &mut TokenIterator<'_>, attributes: &[&str]) {
+    while let Some(token) = it.peek_token() {
+        let token = it.keyword(token);
+        if RESERVED_WORDS.contains(&token) && !attributes.contains(&token) {
+            break;
+        }
+
+        it.eat_token();
+    }
+}
+
+/// Only whitespace between `offset` and the previous newline.
+fn is_line_start(s: &str, offset: usize) -> bool {
+    s[..offset]
+        .chars()
+        .rev()
+        .take_while(|&a| a != '\n')
+        .all(|a| a.is_whitespace() || a == BYTE_ORDER_MARK)
+}
+
+/// Span of `token` starting at `offset`, including the closing quote that tokens leave out.
+fn token_span(offset: usize, token: &str) -> Span {
+    let closing_quote = usize::from(token.starts_with(DOUBLE_QUOTE));
+    Span::new(offset, offset + token.len() + closing_quote)
+}
+
+/// `name@12` into `name` and `12`.
+pub(crate) fn split_stdcall_decoration(s: &str) -> Option<(&str, u64)> {
+    let (name, bytes) = s.rsplit_once('@')?;
+    if name.is_empty() || bytes.is_empty() || !bytes.bytes().all(|a| a.is_ascii_digit()) {
+        return None;
+    }
+
+    Some((name, bytes.parse().ok()?))
+}
+
+/// Reads the `module.entry` target of an import, where both parts can be quoted
+/// as in `"my module".entry` or `module."entry name"`.
+///
+/// A quoted module is a separate token for [`TokenIterator`], so the target is read directly.
+fn eat_import_target<'a>(it: &mut TokenIterator<'a>) -> &'a str {
+    let rest = it.rest;
+
+    let mut end = 0;
+    loop {
+        let s = &rest[end..];
+        if let Some(quoted) = s.strip_prefix(DOUBLE_QUOTE) {
+            match find_closing_quote(quoted) {
+                Some(i) => end += i + 2,
+                None => {
+                    end = rest.trim_end().len();
+                    break;
+                }
+            }
+        } else {
+            let len = s
+                .find(|a: char| a.is_whitespace() || matches!(a, ',' | ':' | '=' | '"'))
+                .unwrap_or(s.len());
+            if len == 0 {
+                break;
+            }
+            end += len;
+        }
+    }
+
+    it.set_rest(end, false);
+    it.remove_comment();
+
+    &rest[..end]
+}
+
+/// Splits an import target at the last period that is not inside of quotes.
+fn split_import_target(target: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    let mut split = None;
+    for (i, c) in target.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '.' if !quoted => split = Some(i),
+            _ => {}
+        }
+    }
+
+    split.map(|i| (&target[..i], &target[i + 1..]))
+}
+
+/// Removes the surrounding quotes of a quoted part of an import target.
+fn strip_quotes(s: &str) -> &str {
+    let Some(quoted) = s.strip_prefix(DOUBLE_QUOTE) else {
+        return s;
+    };
+    quoted.strip_suffix(DOUBLE_QUOTE).unwrap_or(quoted)
+}
+
+/// Removes the starting quote of a quoted token, the closing quote is never part of the token.
 fn strip_ident(s: &str) -> &str {
-    s.trim_start_matches(DOUBLE_QUOTE)
+    s.strip_prefix(DOUBLE_QUOTE).unwrap_or(s)
 }
 
 struct Wrapper<'a> {
@@ -739,6 +1315,13 @@ impl<'a> core::fmt::Write for Wrapper<'a> {
     }
 }
 
+/// Returns [`true`] if `s` can not be written without surrounding quotes.
+///
+/// Keywords in any case are quoted, so that the file can also be parsed
+/// with [`ParseOptions::case_insensitive`].
 pub(crate) fn needs_quotes(s: &str) -> bool {
-    s.contains(' ') || s.contains(';')
+    s.is_empty()
+        || s.contains(|a: char| a.is_whitespace() || matches!(a, ';' | '=' | ',' | ':' | '"'))
+        || s.starts_with('@')
+        || is_keyword(keyword(s, true))
 }
@@ -0,0 +1,315 @@
+use crate::{Export, ModuleDefinitionFile};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+const SECTION_HEADER_SIZE: usize = 40;
+const EXPORT_DIRECTORY_SIZE: usize = 40;
+const DATA_DIRECTORY_SIZE: usize = 8;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// Problem found by [`ModuleDefinitionFile::from_pe`].
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum PeError {
+    /// The image does not start with an `MZ` header.
+    InvalidDosHeader,
+    /// The `PE\0\0` signature is missing.
+    InvalidSignature,
+    /// Optional header magic that is neither PE32 nor PE32+.
+    UnsupportedOptionalHeader(u16),
+    /// The image has no export directory.
+    MissingExportDirectory,
+    /// Relative virtual address that is not inside of any section in the file.
+    InvalidRva(u32),
+    /// Name that is not valid UTF-8, at the relative virtual address.
+    InvalidName(u32),
+    /// The image ends before the structure at the file offset.
+    Truncated(usize),
+}
+
+impl Display for PeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PeError::InvalidDosHeader => write!(f, "missing 'MZ' header"),
+            PeError::InvalidSignature => write!(f, "missing 'PE' signature"),
+            PeError::UnsupportedOptionalHeader(a) => {
+                write!(f, "unsupported optional header magic '{a:#X}'")
+            }
+            PeError::MissingExportDirectory => write!(f, "image has no export directory"),
+            PeError::InvalidRva(a) => write!(f, "address '{a:#X}' is not inside of a section"),
+            PeError::InvalidName(a) => write!(f, "name at '{a:#X}' is not valid UTF-8"),
+            PeError::Truncated(a) => write!(f, "image ends before offset '{a:#X}'"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PeError {}
+
+impl ModuleDefinitionFile {
+    /// Create a file from the export directory of a PE image, such as a DLL, like `gendef` or `dumpbin /exports`.
+    ///
+    /// * `LIBRARY` is the name in the export directory.
+    /// * `VERSION` is the image version of the optional header, unless it is `0.0`.
+    /// * Every export has its ordinal, exports without a name are `NONAME`.
+    /// * Forwarded exports have the forwarder as [`internal_name`](Export::internal_name).
+    /// * Exports pointing into a section that is not executable are `DATA`.
+    ///
+    /// Exports are in ordinal order.
+    /// When several names share an ordinal, only the first name has the ordinal
+    /// and the others use it as the internal name.
+    ///
+    /// # Errors
+    ///
+    /// If the image is not a valid PE32 or PE32+ image, or if it has no export directory.
+    pub fn from_pe(image: &[u8]) -> Result<Self, PeError> {
+        let pe = Pe::new(image)?;
+
+        let directory = pe.export_directory.ok_or(PeError::MissingExportDirectory)?;
+        let offset = pe.offset(directory.rva, EXPORT_DIRECTORY_SIZE)?;
+
+        let name_rva = read_u32(image, offset + 12)?;
+        let ordinal_base = read_u32(image, offset + 16)?;
+        let number_of_functions = read_u32(image, offset + 20)? as usize;
+        let number_of_names = read_u32(image, offset + 24)? as usize;
+        let functions = read_u32(image, offset + 28)?;
+        let names = read_u32(image, offset + 32)?;
+        let name_ordinals = read_u32(image, offset + 36)?;
+
+        let functions = pe.array(functions, number_of_functions, 4)?;
+        let mut function_names = vec![Vec::new(); number_of_functions];
+        if number_of_names != 0 {
+            let names = pe.array(names, number_of_names, 4)?;
+            let name_ordinals = pe.array(name_ordinals, number_of_names, 2)?;
+
+            for (name, index) in names.chunks_exact(4).zip(name_ordinals.chunks_exact(2)) {
+                let index = usize::from(read_u16(index, 0)?);
+                let name = pe.string(read_u32(name, 0)?)?;
+
+                if let Some(function_names) = function_names.get_mut(index) {
+                    function_names.push(name);
+                }
+            }
+        }
+
+        let mut exports = Vec::new();
+        for (i, (rva, names)) in functions.chunks_exact(4).zip(function_names).enumerate() {
+            let rva = read_u32(rva, 0)?;
+            // Unused entry
+            if rva == 0 {
+                continue;
+            }
+
+            let ordinal = u64::from(ordinal_base) + i as u64;
+            let forward = if directory.contains(rva) {
+                Some(pe.string(rva)?)
+            } else {
+                None
+            };
+            let data = forward.is_none() && !pe.is_executable(rva);
+
+            let mut names = names.into_iter();
+            let Some(first) = names.next() else {
+                // Ordinal only exports still need a name for the linker
+                let name = alloc::format!("Ordinal{ordinal}");
+                exports.push(Export::new(name, forward, Some(ordinal), true, false, data));
+                continue;
+            };
+
+            let internal_name = forward.clone().unwrap_or_else(|| first.clone());
+            exports.push(Export::new(
+                first,
+                forward,
+                Some(ordinal),
+                false,
+                false,
+                data,
+            ));
+            for name in names {
+                let internal_name = Some(internal_name.clone());
+                exports.push(Export::new(name, internal_name, None, false, false, data));
+            }
+        }
+
+        let (major_version, minor_version) = match pe.image_version {
+            (0, 0) => (None, None),
+            (major, minor) => (Some(major), Some(minor)),
+        };
+
+        Ok(Self {
+            name: Some(pe.string(name_rva)?),
+            is_library: Some(true),
+            major_version,
+            minor_version,
+            exports,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct DataDirectory {
+    rva: u32,
+    size: u32,
+}
+
+impl DataDirectory {
+    const fn contains(&self, rva: u32) -> bool {
+        rva >= self.rva && rva - self.rva < self.size
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct SectionHeader {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_size: u32,
+    raw_offset: u32,
+    characteristics: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Pe<'a> {
+    image: &'a [u8],
+    image_version: (u16, u16),
+    export_directory: Option<DataDirectory>,
+    sections: Vec<SectionHeader>,
+}
+
+impl<'a> Pe<'a> {
+    fn new(image: &'a [u8]) -> Result<Self, PeError> {
+        if !image.starts_with(b"MZ") {
+            return Err(PeError::InvalidDosHeader);
+        }
+
+        let pe = read_u32(image, 0x3C)? as usize;
+        let coff = pe.checked_add(4).ok_or(PeError::InvalidSignature)?;
+        if image.get(pe..coff) != Some(b"PE\0\0") {
+            return Err(PeError::InvalidSignature);
+        }
+
+        let number_of_sections = usize::from(read_u16(image, coff + 2)?);
+        let optional_header_size = usize::from(read_u16(image, coff + 16)?);
+
+        let optional = coff + 20;
+        let (number_of_rva_and_sizes, directories) = match read_u16(image, optional)? {
+            PE32_MAGIC => (optional + 92, optional + 96),
+            PE32_PLUS_MAGIC => (optional + 108, optional + 112),
+            magic => return Err(PeError::UnsupportedOptionalHeader(magic)),
+        };
+        let image_version = (
+            read_u16(image, optional + 44)?,
+            read_u16(image, optional + 46)?,
+        );
+
+        // Directories past the end of the optional header do not exist, whatever `NumberOfRvaAndSizes` says
+        let section_table = optional + optional_header_size;
+        let number_of_directories = (read_u32(image, number_of_rva_and_sizes)? as usize)
+            .min(section_table.saturating_sub(directories) / DATA_DIRECTORY_SIZE);
+
+        let export_directory = if number_of_directories == 0 {
+            None
+        } else {
+            Some(DataDirectory {
+                rva: read_u32(image, directories)?,
+                size: read_u32(image, directories + 4)?,
+            })
+        }
+        .filter(|a| a.rva != 0);
+
+        let sections = (0..number_of_sections)
+            .map(|i| {
+                let header = section_table + i * SECTION_HEADER_SIZE;
+                Ok(SectionHeader {
+                    virtual_size: read_u32(image, header + 8)?,
+                    virtual_address: read_u32(image, header + 12)?,
+                    raw_size: read_u32(image, header + 16)?,
+                    raw_offset: read_u32(image, header + 20)?,
+                    characteristics: read_u32(image, header + 36)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            image,
+            image_version,
+            export_directory,
+            sections,
+        })
+    }
+
+    fn section(&self, rva: u32) -> Option<&SectionHeader> {
+        self.sections.iter().find(|s| {
+            rva >= s.virtual_address && rva - s.virtual_address < s.virtual_size.max(s.raw_size)
+        })
+    }
+
+    /// File offset of `len` bytes at `rva`.
+    fn offset(&self, rva: u32, len: usize) -> Result<usize, PeError> {
+        let section = self.section(rva).ok_or(PeError::InvalidRva(rva))?;
+
+        let relative = (rva - section.virtual_address) as usize;
+        match relative.checked_add(len) {
+            Some(end) if end <= section.raw_size as usize => {}
+            _ => return Err(PeError::InvalidRva(rva)),
+        }
+
+        let offset = (section.raw_offset as usize)
+            .checked_add(relative)
+            .ok_or(PeError::InvalidRva(rva))?;
+        match offset.checked_add(len) {
+            Some(end) if end <= self.image.len() => Ok(offset),
+            end => Err(PeError::Truncated(end.unwrap_or(usize::MAX))),
+        }
+    }
+
+    /// `count` elements of `size` bytes at `rva`.
+    fn array(&self, rva: u32, count: usize, size: usize) -> Result<&'a [u8], PeError> {
+        let len = count.checked_mul(size).ok_or(PeError::InvalidRva(rva))?;
+        let offset = self.offset(rva, len)?;
+
+        Ok(&self.image[offset..offset + len])
+    }
+
+    /// Null terminated string at `rva`.
+    fn string(&self, rva: u32) -> Result<String, PeError> {
+        let offset = self.offset(rva, 0)?;
+        let s = &self.image[offset..];
+        let end = s
+            .iter()
+            .position(|&a| a == 0)
+            .ok_or(PeError::Truncated(self.image.len()))?;
+
+        core::str::from_utf8(&s[..end])
+            .map(ToString::to_string)
+            .map_err(|_| PeError::InvalidName(rva))
+    }
+
+    fn is_executable(&self, rva: u32) -> bool {
+        self.section(rva)
+            .map_or(false, |s| s.characteristics & IMAGE_SCN_MEM_EXECUTE != 0)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, PeError> {
+    let end = offset
+        .checked_add(2)
+        .ok_or(PeError::Truncated(usize::MAX))?;
+    match data.get(offset..end) {
+        Some(&[a, b]) => Ok(u16::from_le_bytes([a, b])),
+        _ => Err(PeError::Truncated(end)),
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, PeError> {
+    let end = offset
+        .checked_add(4)
+        .ok_or(PeError::Truncated(usize::MAX))?;
+    match data.get(offset..end) {
+        Some(&[a, b, c, d]) => Ok(u32::from_le_bytes([a, b, c, d])),
+        _ => Err(PeError::Truncated(end)),
+    }
+}
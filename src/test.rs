@@ -1,8 +1,31 @@
-use crate::error::{ParseError, ParseErrorKind};
-use crate::parse_ref;
-use crate::parse_ref::{ExportRef, ModuleDefinitionFileRef, SectionRef};
+use crate::error::{
+    Diagnostic, LineColumn, ParseError, ParseErrorKind, Severity, Span, WriteError,
+};
+use crate::parse_ref::{ExportRef, ExportTarget, ImportRef, ModuleDefinitionFileRef, SectionRef};
+use crate::token_iterator::BYTE_ORDER_MARK;
+use crate::validate::{Lint, LintKind};
+use crate::{
+    parse_ref, parse_ref_recovering, parse_ref_strict, parse_ref_with_options, ParseOptions,
+};
 #[cfg(feature = "alloc")]
-use crate::ModuleDefinitionFile;
+use crate::{ModuleDefinitionFile, SyntaxKind, SyntaxTree};
+
+const TEST_FILES: &[&str] = &[
+    include_str!("../tests/console.def"),
+    include_str!("../tests/custom_actions.def"),
+    include_str!("../tests/dagor.def"),
+    include_str!("../tests/dbgext.def"),
+    include_str!("../tests/GPOWrapper.def"),
+    include_str!("../tests/grpc.def"),
+    include_str!("../tests/hookdll.def"),
+    include_str!("../tests/ImageResizerExt.def"),
+    include_str!("../tests/libtirpc.def"),
+    include_str!("../tests/php_zlib.def"),
+    include_str!("../tests/PowerRenameExt.def"),
+    include_str!("../tests/pyshellext.def"),
+    include_str!("../tests/smdlexp.def"),
+    include_str!("../tests/zlib.def"),
+];
 
 fn p(s: &str) -> ModuleDefinitionFileRef<'_> {
     parse_ref(s).unwrap()
@@ -15,6 +38,191 @@ fn err(s: &str, err: ParseError<'_>) {
     }
 }
 
+#[test]
+fn read_test_files() {
+    for &file in TEST_FILES {
+        let f = p(file);
+        let mut exports = f.exports;
+        assert!(exports.all(|a| a.is_ok()));
+        let mut sections = f.sections;
+        assert!(sections.all(|a| a.is_ok()));
+    }
+
+    // Starts with a byte order mark
+    let mut e = p(include_str!("../tests/GPOWrapper.def")).exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "DllCanUnloadNow",
+            Some("WINRT_CanUnloadNow"),
+            None,
+            false,
+            true,
+            false
+        )))
+    );
+}
+
+#[test]
+fn lexer() {
+    use crate::{Lexer, Token, TokenKind};
+
+    for &file in TEST_FILES {
+        let mut end = 0;
+        for token in Lexer::new(file) {
+            assert_eq!(&file[token.span.start..token.span.end], token.text);
+            assert!(file[end..token.span.start]
+                .trim_start_matches(BYTE_ORDER_MARK)
+                .chars()
+                .all(|a| a.is_whitespace() && a != '\n'));
+            end = token.span.end;
+        }
+        assert!(file[end..].chars().all(char::is_whitespace));
+    }
+
+    const FILE: &str = "LIBRARY \"my \"\"lib\"\"\" BASE=0x1000\r\nSTUB:stub.exe\nEXPORTS\n\tf=kernel32.g @1 read, ;c\r\n";
+    let mut lexer = Lexer::new(FILE);
+    let mut next = |kind, text: &str| {
+        let token = lexer.next().unwrap();
+        assert_eq!(token, Token::new(kind, text, token.span));
+        assert_eq!(&FILE[token.span.start..token.span.end], text);
+        token
+    };
+
+    next(TokenKind::Keyword, "LIBRARY");
+    let name = next(TokenKind::QuotedString, "\"my \"\"lib\"\"\"");
+    assert_eq!(name.unquoted(), "my \"\"lib\"\"");
+    next(TokenKind::Keyword, "BASE");
+    next(TokenKind::Equals, "=");
+    next(TokenKind::Number, "0x1000");
+    next(TokenKind::Newline, "\r\n");
+    next(TokenKind::Keyword, "STUB");
+    next(TokenKind::Colon, ":");
+    next(TokenKind::Identifier, "stub.exe");
+    next(TokenKind::Newline, "\n");
+    next(TokenKind::Keyword, "EXPORTS");
+    next(TokenKind::Newline, "\n");
+    next(TokenKind::Identifier, "f");
+    next(TokenKind::Equals, "=");
+    next(TokenKind::Identifier, "kernel32.g");
+    next(TokenKind::Ordinal, "@1");
+    next(TokenKind::Identifier, "read");
+    next(TokenKind::Comma, ",");
+    next(TokenKind::Comment, ";c");
+    next(TokenKind::Newline, "\r\n");
+    assert_eq!(lexer.next(), None);
+
+    let options = ParseOptions::new().case_insensitive(true);
+    let mut lexer = Lexer::with_options("\u{feff}exports read", options);
+    assert_eq!(
+        lexer.next(),
+        Some(Token::new(TokenKind::Keyword, "exports", Span::new(3, 10)))
+    );
+    assert_eq!(
+        lexer.next(),
+        Some(Token::new(TokenKind::Keyword, "read", Span::new(11, 15)))
+    );
+    assert_eq!(lexer.next(), None);
+
+    // Unterminated string
+    let mut lexer = Lexer::new("NAME \"abc\nEXPORTS");
+    lexer.next();
+    assert_eq!(
+        lexer.next(),
+        Some(Token::new(
+            TokenKind::QuotedString,
+            "\"abc\nEXPORTS",
+            Span::new(5, 17)
+        ))
+    );
+
+    // Non-ASCII text at the end of the file
+    assert!(Lexer::new("\u{e9}").eq([Token::new(
+        TokenKind::Identifier,
+        "\u{e9}",
+        Span::new(0, 2)
+    )]));
+    let mut lexer = Lexer::new("EXPORTS\n    caf\u{e9}\u{3000}");
+    lexer.next();
+    lexer.next();
+    assert_eq!(
+        lexer.next(),
+        Some(Token::new(
+            TokenKind::Identifier,
+            "caf\u{e9}",
+            Span::new(12, 17)
+        ))
+    );
+    assert_eq!(lexer.next(), None);
+}
+
+/// Token kinds must agree with how the parser reads the same tokens.
+#[test]
+fn lexer_agrees_with_parser() {
+    use crate::{Lexer, TokenKind};
+
+    let kinds = |s| Lexer::new(s).map(|a| (a.kind, a.text));
+
+    for &file in TEST_FILES {
+        let mut ordinals = p(file).exports.filter_map(|a| a.unwrap().ordinal);
+        let mut in_exports = false;
+        for (kind, text) in kinds(file) {
+            match kind {
+                TokenKind::Keyword if text != "DATA" && text != "NONAME" && text != "PRIVATE" => {
+                    in_exports = text == "EXPORTS";
+                }
+                TokenKind::Ordinal if in_exports => {
+                    assert_eq!(Some(text[1..].parse().unwrap()), ordinals.next());
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(ordinals.next(), None);
+    }
+
+    // Only numbers after VERSION are split
+    assert!(kinds("VERSION 1.5").eq([
+        (TokenKind::Keyword, "VERSION"),
+        (TokenKind::Number, "1"),
+        (TokenKind::Period, "."),
+        (TokenKind::Number, "5")
+    ]));
+    assert_eq!(
+        (
+            p("VERSION 1.5").major_version,
+            p("VERSION 1.5").minor_version
+        ),
+        (Some(1), Some(5))
+    );
+    assert!(kinds("HEAPSIZE 1.5").eq([
+        (TokenKind::Keyword, "HEAPSIZE"),
+        (TokenKind::Identifier, "1.5")
+    ]));
+    assert!(parse_ref("HEAPSIZE 1.5").is_err());
+
+    // Invalid ordinals are still ordinals, other words starting with '@' are names
+    let file = "EXPORTS\n    @func@8\n    a @1.2\n";
+    assert!(kinds(file).eq([
+        (TokenKind::Keyword, "EXPORTS"),
+        (TokenKind::Newline, "\n"),
+        (TokenKind::Identifier, "@func@8"),
+        (TokenKind::Newline, "\n"),
+        (TokenKind::Identifier, "a"),
+        (TokenKind::Ordinal, "@1.2"),
+        (TokenKind::Newline, "\n"),
+    ]));
+    let mut exports = p(file).exports;
+    assert_eq!(exports.next().unwrap().unwrap().name, "@func@8");
+    assert!(exports.next().unwrap().is_err());
+
+    // Every comment line is skipped by both
+    let file = "EXPORTS\n;a\n;b\n;c\n    f\n";
+    assert_eq!(kinds(file).filter(|a| a.0 == TokenKind::Comment).count(), 3);
+    let mut exports = p(file).exports;
+    assert_eq!(exports.next().unwrap().unwrap().name, "f");
+    assert_eq!(exports.next(), None);
+}
+
 #[test]
 fn read_synthetic() {
     const FILE: &str = "\
@@ -33,6 +241,7 @@ EXPORTS
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn library_name() {
     assert_eq!(p("NAME").is_library.unwrap(), false);
     assert_eq!(p("LIBRARY").is_library.unwrap(), true);
@@ -349,6 +558,40 @@ fn stub() {
     );
 }
 
+#[test]
+fn description() {
+    err("DESCRIPTION ", ParseError::missing_arg("DESCRIPTION", 11));
+
+    assert_eq!(p("DESCRIPTION simple").description.unwrap(), "simple");
+    assert_eq!(p("DESCRIPTION \"HookDLL\"").description.unwrap(), "HookDLL");
+    assert_eq!(
+        p("DESCRIPTION \"EXPORTS; with spaces\" EXPORTS simple")
+            .description
+            .unwrap(),
+        "EXPORTS; with spaces"
+    );
+    assert_eq!(
+        p("DESCRIPTION \"with \"\"quotes\"\"\"")
+            .description
+            .unwrap(),
+        "with \"\"quotes\"\""
+    );
+
+    let f = p(include_str!("../tests/hookdll.def"));
+    assert_eq!(f.description.unwrap(), "HookDLL");
+    assert_eq!(f.name.unwrap(), "HOOKDLL");
+
+    #[cfg(feature = "alloc")]
+    {
+        let f = ModuleDefinitionFile::new("DESCRIPTION \"with \"\"quotes\"\"\"").unwrap();
+        assert_eq!(f.description.as_deref(), Some("with \"quotes\""));
+        assert_eq!(
+            f.write_to_buffer().unwrap(),
+            "DESCRIPTION \"with \"\"quotes\"\"\"\n"
+        );
+    }
+}
+
 #[test]
 fn version() {
     err("VERSION ", ParseError::missing_arg("VERSION", 7));
@@ -388,6 +631,84 @@ fn version() {
     );
 }
 
+#[test]
+fn error_spans() {
+    // Offsets are the start of the offending token, even with trailing text
+    err(
+        "VERSION 65536 EXPORTS a",
+        ParseError::new(ParseErrorKind::NumberTooLarge("65536"), 8),
+    );
+    err(
+        "VERSION 1.65536 EXPORTS a",
+        ParseError::new(ParseErrorKind::NumberTooLarge("65536"), 10),
+    );
+    err(
+        "STACKSIZE 1, 0b11 EXPORTS a",
+        ParseError::new(ParseErrorKind::InvalidNumericalArgument("0b11"), 13),
+    );
+    err(
+        "LIBRARY a BASE=0b1 EXPORTS a",
+        ParseError::new(ParseErrorKind::InvalidNumericalArgument("0b1"), 15),
+    );
+
+    let e = parse_ref("VERSION 65536 EXPORTS a").unwrap_err();
+    assert_eq!(e.span, Span::new(8, 13));
+    assert_eq!(e.offset(), 8);
+    assert_eq!(ParseError::missing_arg("VERSION", 7).span, Span::new(7, 7));
+
+    let mut e = p("EXPORTS\n    name @0x1g").exports;
+    let e = e.next().unwrap().unwrap_err();
+    assert_eq!(e.span, Span::new(18, 22));
+
+    const FILE: &str = "LIBRARY lib\r\n\tHEAPSIZE 0b1\r\n";
+    let e = parse_ref(FILE).unwrap_err();
+    assert_eq!(
+        e.line_column(FILE),
+        LineColumn {
+            line: 2,
+            column: 11
+        }
+    );
+    assert_eq!(
+        Span::new(0, 0).line_column(FILE),
+        LineColumn { line: 1, column: 1 }
+    );
+    assert_eq!(
+        Span::new(FILE.len(), FILE.len()).line_column(FILE),
+        LineColumn { line: 3, column: 1 }
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        use alloc::string::ToString;
+
+        assert_eq!(e.line_column(FILE).to_string(), "2:11");
+        assert_eq!(
+            e.render(FILE),
+            "\
+error: invalid numerical argument '0b1'
+ --> 2:11
+  |
+2 | \tHEAPSIZE 0b1
+  | \t         ^^^
+"
+        );
+
+        const MISSING: &str = "EXPORTS a\nVERSION";
+        let e = parse_ref(MISSING).unwrap_err();
+        assert_eq!(
+            e.render(MISSING),
+            "\
+error: missing argument for 'VERSION'
+ --> 2:8
+  |
+2 | VERSION
+  |        ^
+"
+        );
+    }
+}
+
 #[test]
 fn sections() {
     let mut s = p("SECTIONS .rdata SECTIONS .data").sections;
@@ -576,98 +897,2516 @@ fn exports() {
 }
 
 #[test]
-fn write() {
-    const FILES: &[&str] = &[
-        "\
-LIBRARY test
-",
-        "\
-LIBRARY \"test with spaces\"
-",
-        "\
-NAME test
-",
-        "\
-NAME \"test with spaces\"
-",
-        "\
-NAME test BASE=0x10000
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-STACKSIZE 0xFFFF,0xFDFD
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000
-STACKSIZE 0xFFFF
-STUB:test.x
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-STACKSIZE 0xFFFF,0xFDFD
-VERSION 1.12
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-STACKSIZE 0xFFFF,0xFDFD
-VERSION 1
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-STACKSIZE 0xFFFF,0xFDFD
-VERSION 1
-SECTIONS
-    .rdata READ WRITE EXECUTE SHARED
-    .idata READ EXECUTE
-",
-        "\
-NAME test BASE=0x10000
-HEAPSIZE 0x1000,0x2000
-STACKSIZE 0xFFFF,0xFDFD
-VERSION 1
-SECTIONS
-    .rdata READ WRITE EXECUTE SHARED
-    .idata READ EXECUTE
+fn export_forwarders() {
+    let mut e =
+        p("EXPORTS simple local=inner HeapAlloc = NTDLL.RtlAllocateHeap foo = api.ms.win.#12")
+            .exports;
+
+    let simple = e.next().unwrap().unwrap();
+    assert_eq!(simple.target(), ExportTarget::Symbol("simple"));
+    assert!(!simple.target().is_forward());
+
+    let local = e.next().unwrap().unwrap();
+    assert_eq!(local.target(), ExportTarget::Symbol("inner"));
+
+    let heap_alloc = e.next().unwrap().unwrap();
+    assert_eq!(heap_alloc.internal_name, Some("NTDLL.RtlAllocateHeap"));
+    assert_eq!(
+        heap_alloc.target(),
+        ExportTarget::ForwardName {
+            module: "NTDLL",
+            name: "RtlAllocateHeap"
+        }
+    );
+    assert_eq!(heap_alloc.target().module(), Some("NTDLL"));
+
+    let foo = e.next().unwrap().unwrap();
+    assert_eq!(
+        foo.target(),
+        ExportTarget::ForwardOrdinal {
+            module: "api.ms.win",
+            ordinal: 12
+        }
+    );
+    assert_eq!(e.next(), None);
+
+    let mut e = p("EXPORTS foo = other.#bar").exports;
+    assert_eq!(
+        e.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("bar"),
+            21
+        )))
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        use alloc::string::ToString;
+
+        let f = ModuleDefinitionFile::new("EXPORTS foo = other.#12").unwrap();
+        assert_eq!(f.exports[0].target().to_string(), "other.#12");
+        assert_eq!(
+            f.exports[0].target(),
+            ExportTarget::ForwardOrdinal {
+                module: "other",
+                ordinal: 12
+            }
+        );
+    }
+}
+
+#[test]
+fn gnu_dialect() {
+    use crate::{Dialect, WriteOptions};
+
+    const GNU: ParseOptions = ParseOptions::new().dialect(Dialect::Gnu);
+    const FILE: &str = "\
+LIBRARY mylib.dll
 EXPORTS
-    name
-    name=internal_name
-    name=name_internal @10
-    name=name_internal @10 NONAME
-    name=name_internal @10 NONAME PRIVATE
-    name=name_internal @10 NONAME PRIVATE DATA
-    name=name_internal PRIVATE DATA
-    name=module.name_internal PRIVATE DATA
-    name=module.name_internal DATA
-    data DATA
-",
+    func@12
+    other@8 @3 NONAME
+    renamed == _renamed@4
+    value DATA CONSTANT
+    \"with space@4\"
+    mangled@abc
+";
+
+    let f = parse_ref_with_options(FILE, GNU, |e| panic!("{e}")).unwrap();
+    assert_eq!(f.name, Some("mylib.dll"));
+
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(
+            ExportRef::new("func", None, None, false, false, false).with_argument_bytes(Some(12))
+        ))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "other",
+            None,
+            Some(3),
+            true,
+            false,
+            false
+        )
+        .with_argument_bytes(Some(8))))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "renamed", None, None, false, false, false
+        )
+        .with_import_alias(Some("_renamed@4"))))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(
+            ExportRef::new("value", None, None, false, false, true).with_constant(true)
+        ))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "with space",
+            None,
+            None,
+            false,
+            false,
+            false
+        )
+        .with_argument_bytes(Some(4))))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "mangled@abc",
+            None,
+            None,
+            false,
+            false,
+            false
+        )))
+    );
+    assert_eq!(e.next(), None);
+
+    // Ordinary names and a new export with the default dialect
+    let mut e = p(FILE).exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "func@12", None, None, false, false, false
+        )))
+    );
+    assert!(e.any(|e| e == Ok(ExportRef::new("CONSTANT", None, None, false, false, false))));
+
+    let mut buf = [0_u8; 512];
+    let written = parse_ref_with_options(FILE, GNU, |_| {})
+        .unwrap()
+        .write_to_buffer_with_options(&mut buf, WriteOptions::new().dialect(Dialect::Gnu))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        written,
         "\
+LIBRARY mylib.dll
 EXPORTS
-    name1 @1 NONAME
-    name2 @2 NONAME
-    name3 @3 NONAME
-    name4 @4 NONAME
-    name5 @5 NONAME
-",
-    ];
+    func@12
+    other@8 @3 NONAME
+    renamed == _renamed@4
+    value DATA CONSTANT
+    \"with space@4\"
+    mangled@abc
+"
+    );
 
-    for &file in FILES {
-        #[cfg(feature = "alloc")]
-        let owned = ModuleDefinitionFile::new(file).unwrap();
-        let f = ModuleDefinitionFileRef::new(file).unwrap();
-        let mut buf = [0_u8; 1024];
-        let buf = f.write_to_buffer(&mut buf).unwrap().unwrap();
+    #[cfg(feature = "alloc")]
+    {
+        let (owned, _) = crate::parse_with_options(FILE, GNU).unwrap();
+        assert_eq!(owned.exports[0].name, "func");
+        assert_eq!(owned.exports[0].argument_bytes, Some(12));
+        assert_eq!(owned.exports[2].import_alias.as_deref(), Some("_renamed@4"));
+        assert_eq!(
+            owned
+                .write_to_buffer_with_options(WriteOptions::new().dialect(Dialect::Gnu))
+                .unwrap(),
+            written
+        );
+
+        // Only the decoration is written with the default dialect
+        let written = owned.write_to_buffer().unwrap();
+        assert!(written.contains("    renamed\n    value DATA\n"));
+        assert!(written.contains("    func@12\n"));
+    }
+}
 
-        assert_eq!(file, buf);
-        #[cfg(feature = "alloc")]
-        assert_eq!(file, owned.write_to_buffer().unwrap());
+#[test]
+fn export_as() {
+    const FILE: &str = "\
+LIBRARY arm64ec
+EXPORTS
+    #func EXPORTAS func @1
+    other EXPORTAS \"with space\" DATA
+    last
+";
+
+    let mut e = p(FILE).exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "#func",
+            None,
+            Some(1),
+            false,
+            false,
+            false
+        )
+        .with_export_as(Some("func"))))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("other", None, None, false, false, true)
+            .with_export_as(Some("with space"))))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("last", None, None, false, false, false)))
+    );
+    assert_eq!(e.next(), None);
+
+    assert_eq!(
+        p("EXPORTS\n    func EXPORTAS").exports.next(),
+        Some(Err(ParseError::missing_arg("EXPORTAS", 25)))
+    );
+
+    const WRITTEN: &str = "\
+LIBRARY arm64ec
+EXPORTS
+    #func @1 EXPORTAS func
+    other DATA EXPORTAS \"with space\"
+    last
+";
+    let mut buf = [0_u8; 256];
+    assert_eq!(p(FILE).write_to_buffer(&mut buf).unwrap().unwrap(), WRITTEN);
+
+    #[cfg(feature = "alloc")]
+    {
+        let owned = ModuleDefinitionFile::new(FILE).unwrap();
+        assert_eq!(owned.exports[0].export_as.as_deref(), Some("func"));
+        assert_eq!(owned.write_to_buffer().unwrap(), WRITTEN);
+
+        let tree = SyntaxTree::new(FILE);
+        let exports = tree.exports();
+        assert_eq!(exports.len(), 3);
+        assert_eq!(tree.tokens()[exports[0].export_as.unwrap()].text, "func");
     }
 }
+
+#[test]
+fn imports() {
+    let mut i = p("IMPORTS module.entry").imports;
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "module", Some("entry"), None)))
+    );
+    assert_eq!(i.next(), None);
+
+    let mut i =
+        p("IMPORTS internal=module.entry\n  other = module.@12 VERSION 1.2 IMPORTS module.3")
+            .imports;
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(
+            Some("internal"),
+            "module",
+            Some("entry"),
+            None
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(Some("other"), "module", None, Some(12))))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "module", None, Some(3))))
+    );
+    assert_eq!(i.next(), None);
+
+    let f = p("IMPORTS api.ms.win.entry EXPORTS simple");
+    let mut i = f.imports;
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "api.ms.win", Some("entry"), None)))
+    );
+    assert_eq!(i.next(), None);
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "simple", None, None, false, false, false
+        )))
+    );
+
+    let mut i = p("IMPORTS entry").imports;
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::MissingModuleFor("IMPORTS"),
+            8
+        )))
+    );
+
+    let mut i = p("IMPORTS module.@x").imports;
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("x"),
+            16
+        )))
+    );
+    assert_eq!(i.next(), None);
+
+    // The rest of an invalid import is skipped
+    let f = p("IMPORTS\n  mod.@xyz\nEXPORTS\n  foo\n  bar\n");
+    let mut i = f.imports;
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("xyz"),
+            15
+        )))
+    );
+    assert_eq!(i.next(), None);
+    assert_eq!(f.exports.count(), 2);
+
+    let mut i = p("IMPORTS\n  entry\n  mod.1x\n  mod.entry\n").imports;
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::MissingModuleFor("IMPORTS"),
+            10
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("1x"),
+            22
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some("entry"), None)))
+    );
+    assert_eq!(i.next(), None);
+
+    assert_eq!(
+        parse_ref_strict("IMPORTS\n  a= ;"),
+        Err(ParseError::missing_arg("IMPORTS", 13))
+    );
+}
+
+#[test]
+fn strict() {
+    const FILE: &str = "SECTIONS .data READ\nEXPORTS a @x\n b @1\n c = other.#y\nIMPORTS module";
+
+    let f = p(FILE);
+    let mut errors = f.errors();
+    assert_eq!(
+        errors.next(),
+        Some(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("x"),
+            31
+        ))
+    );
+    assert_eq!(
+        errors.next(),
+        Some(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("y"),
+            51
+        ))
+    );
+    assert_eq!(
+        errors.next(),
+        Some(ParseError::new(
+            ParseErrorKind::MissingModuleFor("IMPORTS"),
+            61
+        ))
+    );
+    assert_eq!(errors.next(), None);
+
+    assert_eq!(
+        parse_ref_strict(FILE),
+        Err(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("x"),
+            31
+        ))
+    );
+    assert!(parse_ref_strict("SECTIONS .data READ\nEXPORTS a @1").is_ok());
+
+    // Errors are in file order regardless of kind
+    const MIXED: &str = "EXPORTS\n a @x\nSECTIONS\n .s CLASS";
+    let mixed = p(MIXED);
+    let mut errors = mixed.errors();
+    let export = ParseError::new(ParseErrorKind::InvalidNumericalArgument("x"), 12);
+    assert_eq!(errors.next(), Some(export));
+    assert_eq!(errors.next(), Some(ParseError::missing_arg("SECTIONS", 32)));
+    assert_eq!(errors.next(), None);
+    assert_eq!(parse_ref_strict(MIXED), Err(export));
+
+    let mut buf = [0_u8; 128];
+    assert_eq!(
+        f.write_to_buffer_strict(&mut buf),
+        Err(WriteError::Parse(ParseError::new(
+            ParseErrorKind::InvalidNumericalArgument("x"),
+            31
+        )))
+    );
+
+    // Errors are skipped when not strict
+    let mut buf = [0_u8; 128];
+    assert_eq!(
+        f.write_to_buffer(&mut buf).unwrap(),
+        Ok("SECTIONS\n    .data READ\nEXPORTS\n    b @1\n")
+    );
+
+    let f = p("EXPORTS a @1");
+    let mut buf = [0_u8; 4];
+    assert_eq!(
+        f.write_to_buffer_strict(&mut buf),
+        Err(WriteError::Format(core::fmt::Error))
+    );
+}
+
+#[test]
+fn unknown_statements() {
+    const STRICT: ParseOptions = ParseOptions::new().strict(true);
+
+    for &file in TEST_FILES {
+        parse_ref_with_options(file, STRICT, |e| panic!("{e}")).unwrap();
+        parse_ref_with_options(file, ParseOptions::new(), |e| panic!("{e}")).unwrap();
+    }
+
+    assert_eq!(
+        parse_ref_with_options("LIBRARY a\nEXPORT myfunc", STRICT, |_| {}),
+        Err(ParseError::with_span(
+            ParseErrorKind::UnknownStatement("EXPORT"),
+            Span::new(10, 16)
+        ))
+    );
+    assert_eq!(
+        parse_ref_with_options("LIBRARY a b", STRICT, |_| {}),
+        Err(ParseError::with_span(
+            ParseErrorKind::UnexpectedToken("b"),
+            Span::new(10, 11)
+        ))
+    );
+    assert_eq!(
+        parse_ref_with_options("EXETYPE WINDOWS", STRICT, |_| {}),
+        Err(ParseError::with_span(
+            ParseErrorKind::UnknownStatement("EXETYPE"),
+            Span::new(0, 7)
+        ))
+    );
+
+    const FILE: &str =
+        "LIBRARY a b\nSTACKSIZ 0x1000\nEXETYPE WINDOWS\nHEAPSIZE 1\nEXPORTS\n  func DATA\n";
+    let mut warnings = [None; 4];
+    let mut i = 0;
+    let f = parse_ref_with_options(FILE, ParseOptions::new(), |e| {
+        warnings[i] = Some(e);
+        i += 1;
+    })
+    .unwrap();
+    assert_eq!(
+        warnings,
+        [
+            Some(ParseError::with_span(
+                ParseErrorKind::UnexpectedToken("b"),
+                Span::new(10, 11)
+            )),
+            Some(ParseError::with_span(
+                ParseErrorKind::UnknownStatement("STACKSIZ"),
+                Span::new(12, 20)
+            )),
+            Some(ParseError::with_span(
+                ParseErrorKind::UnknownStatement("EXETYPE"),
+                Span::new(28, 35)
+            )),
+            None,
+        ]
+    );
+    assert_eq!(f.heap_reserve, Some(1));
+    assert_eq!(f.stack_reserve, None);
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("func", None, None, false, false, true)))
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        let (f, warnings) = crate::parse_with_options(FILE, ParseOptions::new()).unwrap();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(f.exports.len(), 1);
+
+        assert!(crate::parse_with_options(FILE, STRICT).is_err());
+    }
+}
+
+#[test]
+fn case_insensitive() {
+    const OPTIONS: ParseOptions = ParseOptions::new().case_insensitive(true);
+    const FILE: &str = "\
+Library mylib base=0x1000
+heapsize 0x1000
+Version 1.2
+sections
+    .data read Write
+exports
+    myfunc=inner @1 noname
+    other Private Data
+imports
+    kernel32.CreateFileA
+";
+
+    for &file in TEST_FILES {
+        let a = parse_ref_with_options(file, OPTIONS, |e| panic!("{e}")).unwrap();
+        let b = p(file);
+        assert_eq!(a.name, b.name);
+        assert!(a.exports.eq(b.exports));
+        assert!(a.sections.eq(b.sections));
+    }
+
+    let f = parse_ref_with_options(FILE, OPTIONS, |e| panic!("{e}")).unwrap();
+    assert_eq!(f.name, Some("mylib"));
+    assert_eq!(f.is_library, Some(true));
+    assert_eq!(f.base_address, Some(0x1000));
+    assert_eq!(f.heap_reserve, Some(0x1000));
+    assert_eq!(f.major_version, Some(1));
+    assert_eq!(f.minor_version, Some(2));
+
+    let mut s = f.sections;
+    assert_eq!(
+        s.next(),
+        Some(Ok(SectionRef::new(".data", true, true, false, false)))
+    );
+    assert_eq!(s.next(), None);
+
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "myfunc",
+            Some("inner"),
+            Some(1),
+            true,
+            false,
+            false
+        )))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("other", None, None, false, true, true)))
+    );
+    assert_eq!(e.next(), None);
+
+    let mut i = f.imports;
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(
+            None,
+            "kernel32",
+            Some("CreateFileA"),
+            None
+        )))
+    );
+    assert_eq!(i.next(), None);
+
+    // Keywords are names by default
+    let f = parse_ref_with_options("exports\n    data\n", ParseOptions::new(), |_| {}).unwrap();
+    assert_eq!(f.exports.count(), 0);
+    let mut e = p("EXPORTS\n    data data\n").exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("data", None, None, false, false, false)))
+    );
+
+    // Quoted names are never keywords
+    let f = parse_ref_with_options("EXPORTS\n    \"data\" data\n", OPTIONS, |_| {}).unwrap();
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("data", None, None, false, false, true)))
+    );
+
+    // Original spelling is kept in errors
+    assert_eq!(
+        parse_ref_with_options("Exetype WINDOWS", OPTIONS.strict(true), |_| {}),
+        Err(ParseError::with_span(
+            ParseErrorKind::UnknownStatement("Exetype"),
+            Span::new(0, 7)
+        ))
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        use alloc::string::ToString;
+
+        let mut tree = SyntaxTree::with_options(FILE, OPTIONS);
+        assert_eq!(tree.exports().len(), 2);
+        tree.set_export_data(1, false);
+        assert_eq!(
+            tree.to_string(),
+            FILE.replace("other Private Data", "other Private")
+        );
+
+        assert_eq!(SyntaxTree::new(FILE).exports().len(), 0);
+    }
+}
+
+#[test]
+fn recovering() {
+    const FILE: &str =
+        "LIBRARY a\nSTACKSIZE x\nHEAPSIZE 1\nEXETYPE WINDOWS\nEXPORTS\n  f @y\n  g @2\nVERSION 1.2\n";
+
+    let mut diagnostics = [None; 4];
+    let mut i = 0;
+    let f = parse_ref_recovering(FILE, ParseOptions::new(), |d| {
+        diagnostics[i] = Some(d);
+        i += 1;
+    });
+    assert_eq!(
+        diagnostics,
+        [
+            Some(Diagnostic::new(
+                Severity::Error,
+                ParseError::new(ParseErrorKind::InvalidNumericalArgument("x"), 20)
+            )),
+            Some(Diagnostic::new(
+                Severity::Warning,
+                ParseError::new(ParseErrorKind::UnknownStatement("EXETYPE"), 33)
+            )),
+            Some(Diagnostic::new(
+                Severity::Error,
+                ParseError::new(ParseErrorKind::InvalidNumericalArgument("y"), 62)
+            )),
+            None,
+        ]
+    );
+    assert_eq!(f.name, Some("a"));
+    assert_eq!(f.stack_reserve, None);
+    assert_eq!(f.heap_reserve, Some(1));
+    assert_eq!(f.major_version, Some(1));
+    assert_eq!(f.minor_version, Some(2));
+    let mut e = f.exports;
+    assert!(e.next().unwrap().is_err());
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("g", None, Some(2), false, false, false)))
+    );
+    assert_eq!(e.next(), None);
+
+    // Unknown statements are errors when strict
+    let mut severities = [None; 4];
+    let mut i = 0;
+    parse_ref_recovering(FILE, ParseOptions::new().strict(true), |d| {
+        severities[i] = Some(d.severity);
+        i += 1;
+    });
+    assert_eq!(
+        severities,
+        [
+            Some(Severity::Error),
+            Some(Severity::Error),
+            Some(Severity::Error),
+            None
+        ]
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        let (f, diagnostics) = crate::parse_recovering(FILE, ParseOptions::new());
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(f.exports.len(), 1);
+        assert_eq!(f.exports[0].name, "g");
+
+        assert_eq!(
+            diagnostics[1].render(FILE),
+            "\
+warning: unknown statement 'EXETYPE'
+ --> 4:1
+  |
+4 | EXETYPE WINDOWS
+  | ^^^^^^^
+"
+        );
+    }
+}
+
+#[test]
+fn validate() {
+    for &file in TEST_FILES {
+        p(file).validate(|l| {
+            assert_ne!(l.severity, Severity::Error, "{l}");
+        });
+    }
+
+    const FILE: &str = "\
+SECTIONS
+    .data READ
+    .data WRITE
+EXPORTS
+    a @1
+    b @1
+    a
+    c NONAME
+    d @0
+    e @65536
+    \"f\" PRIVATE DATA
+    g @x
+";
+
+    let mut lints = [None; 8];
+    let mut i = 0;
+    p(FILE).validate(|l| {
+        lints[i] = Some(l);
+        i += 1;
+    });
+    assert_eq!(
+        lints,
+        [
+            Some(Lint::new(
+                LintKind::DuplicateOrdinal(1),
+                Some(Span::new(61, 62))
+            )),
+            Some(Lint::new(
+                LintKind::DuplicateExportName("a"),
+                Some(Span::new(70, 71))
+            )),
+            Some(Lint::new(
+                LintKind::NonameWithoutOrdinal("c"),
+                Some(Span::new(76, 77))
+            )),
+            Some(Lint::new(
+                LintKind::OrdinalOutOfRange(0),
+                Some(Span::new(89, 90))
+            )),
+            Some(Lint::new(
+                LintKind::OrdinalOutOfRange(65536),
+                Some(Span::new(98, 99))
+            )),
+            Some(Lint::new(
+                LintKind::PrivateData("f"),
+                Some(Span::new(111, 114))
+            )),
+            Some(Lint::new(
+                LintKind::DuplicateSection(".data"),
+                Some(Span::new(28, 33))
+            )),
+            None,
+        ]
+    );
+    assert_eq!(LintKind::DuplicateOrdinal(1).severity(), Severity::Error);
+    assert_eq!(
+        LintKind::DuplicateSection("a").severity(),
+        Severity::Warning
+    );
+
+    #[cfg(feature = "alloc")]
+    {
+        let f = crate::parse_recovering(FILE, ParseOptions::new()).0;
+        let lints = f.validate();
+        assert_eq!(lints.len(), 7);
+        assert_eq!(lints[0], Lint::new(LintKind::DuplicateOrdinal(1), None));
+        assert!(lints.iter().all(|l| l.span.is_none()));
+
+        let l = Lint::new(LintKind::DuplicateOrdinal(1), Some(Span::new(61, 62)));
+        assert_eq!(
+            l.render(FILE),
+            "\
+error: ordinal '1' is used by more than one export
+ --> 6:5
+  |
+6 |     b @1
+  |     ^
+"
+        );
+        assert_eq!(
+            lints[0].render(FILE),
+            "error: ordinal '1' is used by more than one export\n"
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn assign_ordinals() {
+    use crate::{OrdinalAssignment, OrdinalStrategy};
+
+    const FILE: &str = "EXPORTS\n    a @3\n    b\n    c @1\n    d\n    e\n";
+
+    let mut f = crate::parse(FILE).unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::AppendAfterHighest),
+        [
+            OrdinalAssignment::new(1, 4),
+            OrdinalAssignment::new(3, 5),
+            OrdinalAssignment::new(4, 6),
+        ]
+    );
+    // Nothing left to assign
+    assert_eq!(f.assign_ordinals(OrdinalStrategy::FillGaps), []);
+
+    let mut f = crate::parse(FILE).unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::FillGaps),
+        [
+            OrdinalAssignment::new(1, 2),
+            OrdinalAssignment::new(3, 4),
+            OrdinalAssignment::new(4, 5),
+        ]
+    );
+    assert!(f.validate().is_empty());
+
+    // 'b' keeps its ordinal, 'd' can't since 1 is taken, 'e' is new
+    // and 'x' was removed so 7 is never reused
+    let previous = crate::parse("EXPORTS\n    b @5\n    d @1\n    x @7\n").unwrap();
+    let mut f = crate::parse(FILE).unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::FromPrevious(&previous)),
+        [
+            OrdinalAssignment::new(1, 5),
+            OrdinalAssignment::new(3, 8),
+            OrdinalAssignment::new(4, 9),
+        ]
+    );
+
+    // Ordinals above 65535 are ignored and never assigned
+    const LARGE: &str = "EXPORTS\n    a @18446744073709551615\n    b\n";
+    let mut f = crate::parse(LARGE).unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::AppendAfterHighest),
+        [OrdinalAssignment::new(1, 1)]
+    );
+    let mut f = crate::parse(LARGE).unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::FromPrevious(&crate::parse(LARGE).unwrap())),
+        [OrdinalAssignment::new(1, 1)]
+    );
+    // Ordinal 0 of a previous file is not copied
+    let previous = crate::parse("EXPORTS\n    a @0\n    b @3\n").unwrap();
+    let mut f = crate::parse("EXPORTS\n    a\n    b\n").unwrap();
+    assert_eq!(
+        f.assign_ordinals(OrdinalStrategy::FromPrevious(&previous)),
+        [OrdinalAssignment::new(0, 4), OrdinalAssignment::new(1, 3)]
+    );
+    let mut f = crate::parse("EXPORTS\n    a @65535\n    b\n").unwrap();
+    assert_eq!(f.assign_ordinals(OrdinalStrategy::AppendAfterHighest), []);
+    assert_eq!(f.exports[1].ordinal, None);
+
+    let mut f = crate::parse(include_str!("../tests/grpc.def")).unwrap();
+    let assignments = f.assign_ordinals(OrdinalStrategy::AppendAfterHighest);
+    assert_eq!(assignments.len(), f.exports.len());
+    assert!(f.exports.iter().all(|e| e.ordinal.is_some()));
+    assert!(f.validate().is_empty());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn diff() {
+    use crate::{diff, Change, ChangeKind, Section};
+    use alloc::string::ToString;
+
+    const OLD: &str = "\
+LIBRARY a
+VERSION 1.2
+SECTIONS
+    .shared READ WRITE SHARED
+    .old READ
+EXPORTS
+    removed @1
+    renamed = inner @2
+    moved @3
+    to_data @4
+    hidden
+    ordinal
+";
+    const NEW: &str = "\
+LIBRARY a
+VERSION 1.3
+SECTIONS
+    .shared READ WRITE
+    .new READ
+EXPORTS
+    added @7
+    renamed = inner2 @2
+    moved @5
+    to_data @4 DATA
+    hidden PRIVATE
+    ordinal @6 NONAME
+";
+
+    let old = crate::parse(OLD).unwrap();
+    let new = crate::parse(NEW).unwrap();
+    assert_eq!(diff(&old, &old), []);
+
+    let changes = diff(&old, &new);
+    assert_eq!(
+        changes,
+        [
+            Change::new(ChangeKind::VersionChanged {
+                old: (1, 2),
+                new: (1, 3)
+            }),
+            Change::new(ChangeKind::ExportRemoved(&old.exports[0])),
+            Change::new(ChangeKind::InternalNameChanged {
+                name: "renamed",
+                old: Some("inner"),
+                new: Some("inner2")
+            }),
+            Change::new(ChangeKind::OrdinalChanged {
+                name: "moved",
+                old: Some(3),
+                new: Some(5)
+            }),
+            Change::new(ChangeKind::DataChanged {
+                name: "to_data",
+                data: true
+            }),
+            Change::new(ChangeKind::PrivateChanged {
+                name: "hidden",
+                private: true
+            }),
+            Change::new(ChangeKind::OrdinalChanged {
+                name: "ordinal",
+                old: None,
+                new: Some(6)
+            }),
+            Change::new(ChangeKind::NonameChanged {
+                name: "ordinal",
+                noname: true
+            }),
+            Change::new(ChangeKind::ExportAdded(&new.exports[0])),
+            Change::new(ChangeKind::SectionAttributesChanged {
+                old: &old.sections[0],
+                new: &new.sections[0]
+            }),
+            Change::new(ChangeKind::SectionRemoved(&old.sections[1])),
+            Change::new(ChangeKind::SectionAdded(&new.sections[1])),
+        ]
+    );
+
+    let breaking = changes
+        .iter()
+        .map(|a| a.breaking)
+        .collect::<alloc::vec::Vec<_>>();
+    assert_eq!(
+        breaking,
+        [false, true, false, true, true, true, false, true, false, true, true, false]
+    );
+
+    // Removing an ordinal is breaking, adding one is not
+    let reverse = diff(&new, &old);
+    assert!(reverse.contains(&Change {
+        breaking: true,
+        kind: ChangeKind::OrdinalChanged {
+            name: "ordinal",
+            old: Some(6),
+            new: None
+        }
+    }));
+
+    let mut major = new.clone();
+    major.major_version = Some(2);
+    major.name = None;
+    let changes = diff(&new, &major);
+    assert_eq!(
+        changes,
+        [
+            Change {
+                breaking: true,
+                kind: ChangeKind::NameChanged {
+                    old: Some("a"),
+                    new: None
+                }
+            },
+            Change {
+                breaking: true,
+                kind: ChangeKind::VersionChanged {
+                    old: (1, 3),
+                    new: (2, 3)
+                }
+            },
+        ]
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "breaking: module name changed from 'a' to nothing"
+    );
+
+    let old_section = Section::new(".a".to_string(), true, false, false, false);
+    let new_section = Section::new(".a".to_string(), true, true, false, false);
+    let change = Change::new(ChangeKind::SectionAttributesChanged {
+        old: &old_section,
+        new: &new_section,
+    });
+    assert!(!change.breaking);
+    assert_eq!(
+        change.to_string(),
+        "non-breaking: attributes of section '.a' changed from 'READ' to 'READ WRITE'"
+    );
+
+    // Fields of the GNU dialect and `EXPORTAS` change the imported symbol
+    let options = ParseOptions::new().dialect(crate::Dialect::Gnu);
+    let old = crate::parse_with_options(
+        "EXPORTS\n    f@4\n    g == h\n    c\n    e EXPORTAS x\n",
+        options,
+    )
+    .unwrap()
+    .0;
+    let new = crate::parse_with_options(
+        "EXPORTS\n    f@8\n    g\n    c CONSTANT\n    e EXPORTAS y\n",
+        options,
+    )
+    .unwrap()
+    .0;
+    let changes = diff(&old, &new);
+    assert_eq!(
+        changes,
+        [
+            Change::new(ChangeKind::ArgumentBytesChanged {
+                name: "f",
+                old: Some(4),
+                new: Some(8)
+            }),
+            Change::new(ChangeKind::ImportAliasChanged {
+                name: "g",
+                old: Some("h"),
+                new: None
+            }),
+            Change::new(ChangeKind::ConstantChanged {
+                name: "c",
+                constant: true
+            }),
+            Change::new(ChangeKind::ExportAsChanged {
+                name: "e",
+                old: Some("x"),
+                new: Some("y")
+            }),
+        ]
+    );
+    assert!(changes.iter().all(|a| a.breaking));
+    assert_eq!(
+        changes[0].to_string(),
+        "breaking: argument bytes of export 'f' changed from '4' to '8'"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn merge() {
+    use crate::{ConflictPolicy, MergeConflict, MergeOptions};
+    use alloc::string::ToString;
+
+    const FIRST: &str = "\
+LIBRARY a
+HEAPSIZE 1024
+VERSION 1.0
+SECTIONS
+    .shared READ
+EXPORTS
+    f @1
+    g @2
+IMPORTS
+    kernel32.CreateFileA
+";
+    const SECOND: &str = "\
+LIBRARY a
+STACKSIZE 2048
+VERSION 2.0
+SECTIONS
+    .shared WRITE SHARED
+    .other READ
+EXPORTS
+    f @1
+    g @3
+    h @2
+    i DATA
+IMPORTS
+    kernel32.CreateFileA
+    kernel32.CloseHandle
+";
+
+    let first = crate::parse(FIRST).unwrap();
+    let second = crate::parse(SECOND).unwrap();
+
+    // File is unchanged on error
+    let mut f = first.clone();
+    assert_eq!(
+        f.merge(&second, MergeOptions::new()),
+        Err(MergeConflict::Version)
+    );
+    assert_eq!(f, first);
+    assert_eq!(
+        f.merge(
+            &second,
+            MergeOptions::new().version(ConflictPolicy::FirstWins)
+        ),
+        Err(MergeConflict::Ordinal("g".to_string()))
+    );
+    assert_eq!(
+        MergeConflict::Ordinal("g".to_string()).to_string(),
+        "export 'g' has conflicting ordinals"
+    );
+
+    let mut f = first.clone();
+    f.merge(&second, MergeOptions::new().all(ConflictPolicy::FirstWins))
+        .unwrap();
+    assert_eq!(
+        f.write_to_buffer().unwrap(),
+        "\
+LIBRARY a
+HEAPSIZE 0x400
+STACKSIZE 0x800
+VERSION 1.0
+SECTIONS
+    .shared READ WRITE SHARED
+    .other READ
+EXPORTS
+    f @1
+    g @2
+    h
+    i DATA
+IMPORTS
+    kernel32.CreateFileA
+    kernel32.CloseHandle
+"
+    );
+
+    let mut f = first.clone();
+    f.merge(&second, MergeOptions::new().all(ConflictPolicy::LastWins))
+        .unwrap();
+    assert_eq!(f.major_version, Some(2));
+    let ordinals = f
+        .exports
+        .iter()
+        .map(|e| e.ordinal)
+        .collect::<alloc::vec::Vec<_>>();
+    assert_eq!(ordinals, [Some(1), Some(3), Some(2), None]);
+
+    // 'h' takes ordinal 2 from 'g' before 'g' is moved to 3
+    let mut f = first.clone();
+    let options = MergeOptions::new()
+        .version(ConflictPolicy::LastWins)
+        .ordinals(ConflictPolicy::LastWins);
+    f.merge(&second, options).unwrap();
+    assert_eq!(f.exports[1].ordinal, Some(3));
+    assert_eq!(f.exports[2].ordinal, Some(2));
+
+    let other = crate::parse("NAME b\nEXPORTS\n    f = inner @1\n").unwrap();
+    let mut f = first.clone();
+    assert_eq!(
+        f.merge(&other, MergeOptions::new()),
+        Err(MergeConflict::Name)
+    );
+    assert_eq!(
+        f.merge(&other, MergeOptions::new().name(ConflictPolicy::LastWins)),
+        Err(MergeConflict::Export("f".to_string()))
+    );
+    f.merge(
+        &other,
+        MergeOptions::new()
+            .name(ConflictPolicy::LastWins)
+            .exports(ConflictPolicy::LastWins),
+    )
+    .unwrap();
+    assert_eq!(f.name, Some("b".to_string()));
+    assert_eq!(f.is_library, Some(false));
+    assert_eq!(f.exports[0].internal_name, Some("inner".to_string()));
+
+    let merged =
+        crate::ModuleDefinitionFile::merge_all([&first, &first, &first], MergeOptions::new())
+            .unwrap();
+    assert_eq!(merged, first);
+    assert_eq!(
+        crate::ModuleDefinitionFile::merge_all([], MergeOptions::new()),
+        Ok(crate::ModuleDefinitionFile::default())
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn builder() {
+    use crate::BuildError;
+    use alloc::string::ToString;
+
+    let file = ModuleDefinitionFile::library("my lib")
+        .base(0x1000_0000)
+        .heap(0x1000, Some(0x100))
+        .stack(1 << 20, None)
+        .stub("C:\\stub.exe")
+        .description("My \"library\"")
+        .version(1, 2)
+        .section(".shared")
+        .read()
+        .write()
+        .shared()
+        .export("bar")
+        .ordinal(3)
+        .data()
+        .export("with space")
+        .internal_name("EXPORTS")
+        .ordinal(4)
+        .noname()
+        .private()
+        .import("kernel32", "CreateFileA")
+        .import_ordinal("user32", 10)
+        .import("api.ms.win", "entry.name")
+        .import("my module", "EXPORTS")
+        .build()
+        .unwrap();
+
+    const EXPECTED: &str = "\
+LIBRARY \"my lib\" BASE=0x10000000
+HEAPSIZE 0x1000,0x100
+STACKSIZE 0x100000
+STUB:\"C:\\stub.exe\"
+DESCRIPTION \"My \"\"library\"\"\"
+VERSION 1.2
+SECTIONS
+    .shared READ WRITE SHARED
+EXPORTS
+    bar @3 DATA
+    \"with space\"=\"EXPORTS\" @4 NONAME PRIVATE
+IMPORTS
+    kernel32.CreateFileA
+    user32.@10
+    api.ms.win.\"entry.name\"
+    \"my module\".\"EXPORTS\"
+";
+    assert_eq!(file.write_to_buffer().unwrap(), EXPECTED);
+    // Same as when parsing the written file
+    assert_eq!(crate::parse(EXPECTED).unwrap(), file);
+    assert_eq!(file.exports[1].name, "with space");
+
+    let mut buf = [0_u8; 512];
+    let written = p(EXPECTED).write_to_buffer(&mut buf).unwrap().unwrap();
+    assert_eq!(written, EXPECTED);
+
+    assert_eq!(
+        ModuleDefinitionFile::builder().build(),
+        Ok(ModuleDefinitionFile::default())
+    );
+    assert_eq!(
+        ModuleDefinitionFile::builder().base(0x1000).build(),
+        Err(BuildError::BaseWithoutName)
+    );
+    assert_eq!(
+        ModuleDefinitionFile::library("a")
+            .version(1, 0x10000)
+            .build(),
+        Err(BuildError::VersionTooLarge(0x10000))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::library("a")
+            .export("f")
+            .ordinal(0)
+            .build(),
+        Err(BuildError::OrdinalOutOfRange(0))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::library("a")
+            .import_ordinal("user32", 0x10000)
+            .build(),
+        Err(BuildError::OrdinalOutOfRange(0x10000))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::library("a")
+            .export("f")
+            .noname()
+            .build(),
+        Err(BuildError::NonameWithoutOrdinal("f".to_string()))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::library("a")
+            .data()
+            .export("f")
+            .build(),
+        Err(BuildError::MissingItem("EXPORTS"))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::application("a\nb").build(),
+        Err(BuildError::InvalidName("a\nb".to_string()))
+    );
+    // First error is kept
+    let e = ModuleDefinitionFile::library("a")
+        .section("")
+        .read()
+        .import("kernel32.dll", "f")
+        .build()
+        .unwrap_err();
+    assert_eq!(e, BuildError::InvalidName("".to_string()));
+    assert_eq!(e.to_string(), "invalid name ''");
+}
+
+#[test]
+fn write() {
+    const FILES: &[&str] = &[
+        "\
+LIBRARY test
+",
+        "\
+LIBRARY \"test with spaces\"
+",
+        "\
+NAME test
+",
+        "\
+NAME \"test with spaces\"
+",
+        "\
+NAME test BASE=0x10000
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+STACKSIZE 0xFFFF,0xFDFD
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000
+STACKSIZE 0xFFFF
+STUB:test.x
+",
+        "\
+NAME test BASE=0x10000
+STUB:test.x
+DESCRIPTION \"Description; with \"\"quotes\"\"\"
+VERSION 1.12
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+STACKSIZE 0xFFFF,0xFDFD
+VERSION 1.12
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+STACKSIZE 0xFFFF,0xFDFD
+VERSION 1
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+STACKSIZE 0xFFFF,0xFDFD
+VERSION 1
+SECTIONS
+    .rdata READ WRITE EXECUTE SHARED
+    .idata READ EXECUTE
+",
+        "\
+NAME test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+STACKSIZE 0xFFFF,0xFDFD
+VERSION 1
+SECTIONS
+    .rdata READ WRITE EXECUTE SHARED
+    .idata READ EXECUTE
+EXPORTS
+    \"name\"
+    \"name\"=internal_name
+    \"name\"=name_internal @10
+    \"name\"=name_internal @10 NONAME
+    \"name\"=name_internal @10 NONAME PRIVATE
+    \"name\"=name_internal @10 NONAME PRIVATE DATA
+    \"name\"=name_internal PRIVATE DATA
+    \"name\"=module.name_internal PRIVATE DATA
+    \"name\"=module.name_internal DATA
+    \"name\"=module.#12
+    \"name\"=module.#12 @3 NONAME
+    \"data\" DATA
+IMPORTS
+    module.entry
+    internal=module.entry
+    internal=module.@12
+",
+        "\
+EXPORTS
+    name1 @1 NONAME
+    name2 @2 NONAME
+    name3 @3 NONAME
+    name4 @4 NONAME
+    name5 @5 NONAME
+",
+    ];
+
+    for &file in FILES {
+        #[cfg(feature = "alloc")]
+        let owned = ModuleDefinitionFile::new(file).unwrap();
+        let f = ModuleDefinitionFileRef::new(file).unwrap();
+        let mut buf = [0_u8; 1024];
+        let buf = f.write_to_buffer(&mut buf).unwrap().unwrap();
+
+        assert_eq!(file, buf);
+        #[cfg(feature = "alloc")]
+        assert_eq!(file, owned.write_to_buffer().unwrap());
+    }
+}
+
+#[test]
+fn write_with_options() {
+    use crate::{ExportOrder, Indent, KeywordCase, LineEnding, Radix, WriteOptions};
+
+    const FILE: &str = "\
+LIBRARY test BASE=0x10000
+HEAPSIZE 0x1000,0x2000
+SECTIONS
+    .rdata READ SHARED
+EXPORTS
+    b=inner @3 NONAME
+    a
+    long_name @1 DATA
+IMPORTS
+    internal=module.entry
+";
+
+    let options = WriteOptions::new()
+        .indent(Indent::Spaces(2))
+        .radix(Radix::Decimal)
+        .align_ordinals(true)
+        .spaces_around_equals(true)
+        .keyword_case(KeywordCase::Lower)
+        .line_ending(LineEnding::CrLf);
+    let mut buf = [0_u8; 1024];
+    let written = p(FILE)
+        .write_to_buffer_with_options(&mut buf, options)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        written,
+        "\
+library test base=65536\r
+heapsize 4096,8192\r
+sections\r
+  .rdata read shared\r
+exports\r
+  b = inner @3 noname\r
+  a\r
+  long_name @1 data\r
+imports\r
+  internal = module.entry\r
+"
+    );
+
+    let options = WriteOptions::new().export_order(ExportOrder::Ordinal);
+    let written = p(FILE)
+        .write_to_buffer_with_options(&mut buf, options)
+        .unwrap()
+        .unwrap();
+    assert!(written.contains("EXPORTS\n    long_name @1 DATA\n    b=inner @3 NONAME\n    a\n"));
+
+    let options = WriteOptions::new().export_order(ExportOrder::Name);
+    let written = p(FILE)
+        .write_to_buffer_with_options(&mut buf, options)
+        .unwrap()
+        .unwrap();
+    assert!(written.contains("EXPORTS\n    a\n    b=inner @3 NONAME\n    long_name @1 DATA\n"));
+
+    // Same layout as the original file
+    const DAGOR: &str = include_str!("../tests/dagor.def");
+    let options = WriteOptions::new().indent(Indent::Tab).align_ordinals(true);
+    let written = p(DAGOR)
+        .write_to_buffer_with_options(&mut buf, options)
+        .unwrap()
+        .unwrap();
+    let (exports, sections) = DAGOR.split_at(DAGOR.find("SECTIONS").unwrap());
+    assert_eq!(written, alloc_free_concat(sections, exports, &mut [0; 512]));
+
+    #[cfg(feature = "alloc")]
+    assert_eq!(
+        ModuleDefinitionFile::new(DAGOR)
+            .unwrap()
+            .write_to_buffer_with_options(options)
+            .unwrap(),
+        written
+    );
+}
+
+#[test]
+fn write_to() {
+    let mut buf = [0_u8; 16384];
+    for &file in TEST_FILES {
+        let f = p(file);
+        let len = f.written_len();
+        let written = f.write_to_buffer(&mut buf[..len]).unwrap().unwrap();
+        assert_eq!(written.len(), len);
+        assert!(f.write_to_buffer(&mut buf[..len - 1]).is_err());
+
+        #[cfg(feature = "alloc")]
+        {
+            use alloc::string::{String, ToString};
+
+            let written = f.write_to_buffer(&mut buf).unwrap().unwrap();
+
+            let mut s = String::new();
+            f.write_to(&mut s).unwrap();
+            assert_eq!(s, written);
+            assert_eq!(f.to_string(), written);
+
+            let owned = ModuleDefinitionFile::new(file).unwrap();
+            assert_eq!(owned.to_string(), written);
+            assert_eq!(owned.written_len(), len);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    {
+        use crate::IoWriter;
+        use alloc::vec::Vec;
+
+        let f = p("LIBRARY mylib\nEXPORTS\n    myfunc @1\n");
+        let mut w = IoWriter::new(Vec::new());
+        f.write_to(&mut w).unwrap();
+        assert!(w.take_error().is_none());
+        assert_eq!(w.into_inner(), b"LIBRARY mylib\nEXPORTS\n    myfunc @1\n");
+
+        let mut small = [0_u8; 4];
+        let mut w = IoWriter::new(&mut small[..]);
+        assert!(f.write_to(&mut w).is_err());
+        assert_eq!(
+            w.take_error().unwrap().kind(),
+            std::io::ErrorKind::WriteZero
+        );
+    }
+}
+
+#[test]
+fn quoting() {
+    const FILE: &str = "\
+NAME \"with space\"
+STUB:\"a \"\"stub\"\"\"
+SECTIONS
+    \".my section\" READ
+EXPORTS
+    \"a\"\"b\"=\"internal name\" @1
+    \"DATA\" DATA
+    plain
+";
+    let f = p(FILE);
+    assert_eq!(f.name, Some("with space"));
+    assert_eq!(f.stub, Some("a \"\"stub\"\""));
+    let mut sections = f.sections;
+    assert_eq!(
+        sections.next(),
+        Some(Ok(SectionRef::new(
+            ".my section",
+            true,
+            false,
+            false,
+            false
+        )))
+    );
+
+    let mut e = f.exports;
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new(
+            "a\"\"b",
+            Some("internal name"),
+            Some(1),
+            false,
+            false,
+            false
+        )))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("DATA", None, None, false, false, true)))
+    );
+    assert_eq!(
+        e.next(),
+        Some(Ok(ExportRef::new("plain", None, None, false, false, false)))
+    );
+
+    let mut buf = [0_u8; 256];
+    let written = f.write_to_buffer(&mut buf).unwrap().unwrap();
+    assert_eq!(written, FILE);
+
+    // Either part of an import target can be quoted
+    const IMPORTS: &str = "\
+IMPORTS
+    \"my name\"=mod.entry
+    alias=\"my mod.dll\".entry
+    x=mod.\"a b\"
+    \"a\"\"b\"=\"c\"\"d\".\"1st\"
+    mod.\"e.f\"
+    mod.\"g=h\"
+    mod.\":BASE,x\"
+    mod.\",EXPORTS x\"
+";
+    let f = p(IMPORTS);
+    // Quoted parts are not read as statements when skipping the section
+    parse_ref_with_options(IMPORTS, ParseOptions::new(), |e| panic!("{e}")).unwrap();
+    assert_eq!(f.exports.count(), 0);
+    let mut i = f.imports;
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(
+            Some("my name"),
+            "mod",
+            Some("entry"),
+            None
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(
+            Some("alias"),
+            "my mod.dll",
+            Some("entry"),
+            None
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(Some("x"), "mod", Some("a b"), None)))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(
+            Some("a\"\"b"),
+            "c\"\"d",
+            Some("1st"),
+            None
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some("e.f"), None)))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some("g=h"), None)))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some(":BASE,x"), None)))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some(",EXPORTS x"), None)))
+    );
+    assert_eq!(i.next(), None);
+
+    let written = f.write_to_buffer(&mut buf).unwrap().unwrap();
+    assert_eq!(written, IMPORTS);
+
+    // Iteration ends after tokens that are not targets
+    let f = p("IMPORTS\n    , mod.entry\n");
+    let mut i = f.imports;
+    assert_eq!(
+        i.next(),
+        Some(Err(ParseError::new(
+            ParseErrorKind::MissingModuleFor("IMPORTS"),
+            12
+        )))
+    );
+    assert_eq!(
+        i.next(),
+        Some(Ok(ImportRef::new(None, "mod", Some("entry"), None)))
+    );
+    assert_eq!(i.next(), None);
+
+    // Names starting with a reserved word are not reserved words
+    assert_eq!(p("LIBRARY BASELINE\n").name, Some("BASELINE"));
+    assert_eq!(p("LIBRARY BASE=0x1000\n").name, None);
+
+    #[cfg(feature = "alloc")]
+    {
+        let file = crate::parse(FILE).unwrap();
+        assert_eq!(file.stub.as_deref(), Some("a \"stub\""));
+        assert_eq!(file.exports[0].name, "a\"b");
+        assert_eq!(file.write_to_buffer().unwrap(), FILE);
+
+        let file = crate::parse(IMPORTS).unwrap();
+        assert_eq!(file.imports[3].internal_name.as_deref(), Some("a\"b"));
+        assert_eq!(file.imports[3].module, "c\"d");
+        assert_eq!(file.write_to_buffer().unwrap(), IMPORTS);
+    }
+}
+
+/// Every name written must be read back unchanged.
+#[cfg(feature = "alloc")]
+#[test]
+fn quoting_round_trip() {
+    use crate::parse_ref::split_stdcall_decoration;
+    use crate::{Dialect, Export, Import, KeywordCase, Section, WriteOptions};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    const PIECES: &[&str] = &[
+        "a", "Z", "_", "0", "1", "0x1", " ", "\t", "\n", "\"", "\"\"", ";", "=", "==", ",", ":",
+        "@", "@1", ".", "-", "?", "\u{e9}", "\u{3000}", "DATA", "data", "BASE", "EXPORTS",
+        "exports", "Library", "READ", "CONSTANT", "EXPORTAS", "NONAME",
+    ];
+
+    // xorshift, deterministic so that failures can be reproduced
+    let mut state = 0x2545_F491_4F6C_DD1D_u64;
+    let mut next = move |n: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+    let string = |next: &mut dyn FnMut(usize) -> usize| {
+        let mut s = String::new();
+        for _ in 0..=next(4) {
+            s.push_str(PIECES[next(PIECES.len())]);
+        }
+        s
+    };
+
+    for _ in 0..500 {
+        let dialect = if next(2) == 0 {
+            Dialect::Msvc
+        } else {
+            Dialect::Gnu
+        };
+
+        let exports: Vec<Export> = (0..next(4))
+            .map(|_| {
+                let name = string(&mut next);
+                let internal_name = (next(2) == 0).then(|| string(&mut next));
+                let ordinal = (next(2) == 0).then(|| next(0xFFFF) as u64 + 1);
+                let export_as = (next(4) == 0).then(|| string(&mut next));
+
+                let export = Export::new(
+                    name,
+                    internal_name,
+                    ordinal,
+                    ordinal.is_some() && next(2) == 0,
+                    next(4) == 0,
+                    next(4) == 0,
+                )
+                .with_export_as(export_as);
+
+                if dialect == Dialect::Gnu {
+                    // A name ending in a stdcall decoration is always read as one
+                    let argument_bytes = split_stdcall_decoration(&export.name)
+                        .map(|_| 0)
+                        .or_else(|| (next(4) == 0).then(|| next(64) as u64));
+                    export
+                        .with_argument_bytes(argument_bytes)
+                        .with_import_alias((next(4) == 0).then(|| string(&mut next)))
+                        .with_constant(next(4) == 0)
+                } else {
+                    export
+                }
+            })
+            .collect();
+        let sections = (0..next(3))
+            .map(|_| Section::new(string(&mut next), next(2) == 0, next(2) == 0, false, false))
+            .collect();
+        let imports = (0..next(3))
+            .map(|_| {
+                let internal_name = (next(2) == 0).then(|| string(&mut next));
+                let (entry_name, ordinal) = if next(2) == 0 {
+                    (Some(string(&mut next)), None)
+                } else {
+                    (None, Some(next(0xFFFF) as u64))
+                };
+
+                Import::new(internal_name, string(&mut next), entry_name, ordinal)
+            })
+            .collect();
+
+        let file = ModuleDefinitionFile {
+            name: Some(string(&mut next)),
+            is_library: Some(next(2) == 0),
+            stub: (next(2) == 0).then(|| string(&mut next)),
+            description: (next(2) == 0).then(|| string(&mut next)),
+            sections,
+            exports,
+            imports,
+            ..Default::default()
+        };
+
+        // Lower case keywords can only be read back case-insensitively
+        let (keyword_case, case_insensitive) = if next(2) == 0 {
+            (KeywordCase::Upper, next(2) == 0)
+        } else {
+            (KeywordCase::Lower, true)
+        };
+
+        let write_options = WriteOptions::new()
+            .dialect(dialect)
+            .keyword_case(keyword_case);
+        let parse_options = ParseOptions::new()
+            .dialect(dialect)
+            .case_insensitive(case_insensitive);
+        let written = file.write_to_buffer_with_options(write_options).unwrap();
+        assert_eq!(
+            crate::parse_with_options(&written, parse_options),
+            Ok((file.clone(), Vec::new())),
+            "{written}"
+        );
+
+        // Ref writer keeps the escaped names as they are
+        let mut buf = [0_u8; 4096];
+        let rewritten = parse_ref_with_options(&written, parse_options, |e| panic!("{e}"))
+            .unwrap()
+            .write_to_buffer_with_options(&mut buf, write_options)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rewritten, written);
+    }
+}
+
+/// DLL with an export directory in a non-executable `.rdata` section.
+#[cfg(feature = "alloc")]
+fn pe_image(pe32_plus: bool) -> alloc::vec::Vec<u8> {
+    fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+        image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+    fn put_u32(image: &mut [u8], offset: usize, values: &[u32]) {
+        for (i, value) in values.iter().enumerate() {
+            put(image, offset + i * 4, &value.to_le_bytes());
+        }
+    }
+
+    let mut image = alloc::vec![0_u8; 0x800];
+    put(&mut image, 0, b"MZ");
+    put_u32(&mut image, 0x3C, &[0x40]);
+    put(&mut image, 0x40, b"PE\0\0");
+
+    let (magic, optional_size, directories): (u16, u16, usize) = if pe32_plus {
+        (0x20B, 240, 108)
+    } else {
+        (0x10B, 224, 92)
+    };
+    put(&mut image, 0x46, &2_u16.to_le_bytes());
+    put(&mut image, 0x54, &optional_size.to_le_bytes());
+
+    let optional = 0x58;
+    put(&mut image, optional, &magic.to_le_bytes());
+    put(&mut image, optional + 44, &2_u16.to_le_bytes());
+    put(&mut image, optional + 46, &5_u16.to_le_bytes());
+    put_u32(&mut image, optional + directories, &[16, 0x2000, 0x70]);
+
+    // .text and .rdata
+    let sections = optional + usize::from(optional_size);
+    put_u32(&mut image, sections + 8, &[0x100, 0x1000, 0x200, 0x400]);
+    put_u32(&mut image, sections + 36, &[0x6000_0020]);
+    put_u32(&mut image, sections + 48, &[0x200, 0x2000, 0x200, 0x600]);
+    put_u32(&mut image, sections + 76, &[0x4000_0040]);
+
+    let rdata = |rva: usize| rva - 0x2000 + 0x600;
+    // Name, ordinal base, functions, names and the three tables
+    put_u32(
+        &mut image,
+        rdata(0x200C),
+        &[0x2100, 5, 5, 4, 0x2028, 0x203C, 0x204C],
+    );
+    put_u32(
+        &mut image,
+        rdata(0x2028),
+        &[0x1000, 0x2180, 0, 0x1010, 0x2060],
+    );
+    put_u32(&mut image, rdata(0x203C), &[0x2070, 0x2078, 0x2080, 0x2088]);
+    for (i, index) in [0_u16, 4, 0, 1].iter().enumerate() {
+        put(&mut image, rdata(0x204C) + i * 2, &index.to_le_bytes());
+    }
+    put(&mut image, rdata(0x2060), b"kernel32.Sleep\0");
+    put(&mut image, rdata(0x2070), b"alias\0");
+    put(&mut image, rdata(0x2078), b"fwd\0");
+    put(&mut image, rdata(0x2080), b"func\0");
+    put(&mut image, rdata(0x2088), b"var\0");
+    put(&mut image, rdata(0x2100), b"test.dll\0");
+
+    image
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn from_pe() {
+    use crate::PeError;
+
+    const EXPECTED: &str = "\
+LIBRARY test.dll
+VERSION 2.5
+EXPORTS
+    alias @5
+    func=alias
+    var @6 DATA
+    Ordinal8 @8 NONAME
+    fwd=kernel32.Sleep @9
+";
+
+    for pe32_plus in [false, true] {
+        let image = pe_image(pe32_plus);
+        let file = ModuleDefinitionFile::from_pe(&image).unwrap();
+        assert_eq!(file.write_to_buffer().unwrap(), EXPECTED);
+        assert_eq!(crate::parse(EXPECTED).unwrap(), file);
+    }
+
+    let mut image = pe_image(true);
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&image[..0x300]),
+        Err(PeError::Truncated(0x628))
+    );
+
+    // Sizes from the image are not trusted
+    let mut overflow = image.clone();
+    overflow[0x614..0x618].fill(0xFF);
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&overflow),
+        Err(PeError::InvalidRva(0x2028))
+    );
+    let mut overflow = image.clone();
+    overflow[0x3C..0x40].fill(0xFF);
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&overflow),
+        Err(PeError::InvalidSignature)
+    );
+
+    // `NumberOfRvaAndSizes` larger than what fits in `SizeOfOptionalHeader`
+    let mut directories = image.clone();
+    directories[0x54..0x56].copy_from_slice(&112_u16.to_le_bytes());
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&directories),
+        Err(PeError::MissingExportDirectory)
+    );
+
+    // Export directory outside of any section
+    image[0x58 + 112..0x58 + 116].copy_from_slice(&0x8000_u32.to_le_bytes());
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&image),
+        Err(PeError::InvalidRva(0x8000))
+    );
+
+    image[0x58 + 112..0x58 + 116].fill(0);
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&image),
+        Err(PeError::MissingExportDirectory)
+    );
+
+    image[0x40] = b'X';
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(&image),
+        Err(PeError::InvalidSignature)
+    );
+    assert_eq!(
+        ModuleDefinitionFile::from_pe(b"ELF"),
+        Err(PeError::InvalidDosHeader)
+    );
+}
+
+/// Name and contents of every member of an archive.
+#[cfg(feature = "alloc")]
+fn archive_members(archive: &[u8]) -> alloc::vec::Vec<(&str, &[u8])> {
+    assert!(archive.starts_with(b"!<arch>\n"));
+
+    let mut members = alloc::vec::Vec::new();
+    let mut rest = &archive[8..];
+    while !rest.is_empty() {
+        let header = core::str::from_utf8(&rest[..60]).unwrap();
+        let size: usize = header[48..58].trim_end().parse().unwrap();
+        members.push((header[..16].trim_end(), &rest[60..60 + size]));
+        rest = &rest[60 + size + size % 2..];
+    }
+
+    members
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn import_library() {
+    use crate::{Export, ImportLibraryError, Machine};
+
+    let file = crate::parse(
+        "\
+LIBRARY test
+EXPORTS
+    func @5
+    var @6 DATA
+    hidden @7 NONAME
+    alias=func @8
+    internal PRIVATE
+",
+    )
+    .unwrap();
+
+    let library = file.to_import_library(Machine::X64).unwrap();
+    let members = archive_members(&library);
+    let names = members.iter().map(|m| m.0).collect::<alloc::vec::Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "/",
+            "/",
+            "//",
+            "test.dll/",
+            "test.dll/",
+            "test.dll/",
+            "test.dll/",
+            "test.dll/",
+            "test.dll/",
+            "test.dll/"
+        ]
+    );
+
+    // Second linker member has the symbols sorted
+    let symbols = members[1]
+        .1
+        .split(|&a| a == 0)
+        .filter(|a| a.starts_with(b"__imp_"));
+    assert!(symbols.eq([
+        b"__imp_alias".as_slice(),
+        b"__imp_func",
+        b"__imp_hidden",
+        b"__imp_var"
+    ]));
+
+    assert_eq!(
+        members[6].1,
+        b"\0\0\xFF\xFF\0\0\x64\x86\0\0\0\0\x0E\0\0\0\x05\0\x04\0func\0test.dll\0"
+    );
+    assert_eq!(
+        members[7].1,
+        b"\0\0\xFF\xFF\0\0\x64\x86\0\0\0\0\x0D\0\0\0\x06\0\x05\0var\0test.dll\0"
+    );
+    // Imported by ordinal
+    assert_eq!(members[8].1[16..20], [7, 0, 0, 0]);
+
+    // Prefixed with an underscore which is removed when importing
+    let library = file.to_import_library(Machine::X86).unwrap();
+    let func = archive_members(&library)[6].1;
+    assert_eq!(func[6..8], [0x4C, 0x01]);
+    assert_eq!(func[18..], *b"\x08\0_func\0test.dll\0");
+
+    // Mangled symbol that imports the function by its plain name
+    let library = file.to_import_library(Machine::Arm64Ec).unwrap();
+    let members = archive_members(&library);
+    assert_eq!(members[2].0, "/<ECSYMBOLS>/");
+    assert_eq!(members[7].1[18..], *b"\x10\0#func\0test.dll\0func\0");
+
+    assert_eq!(
+        ModuleDefinitionFile::default().to_import_library(Machine::X64),
+        Err(ImportLibraryError::MissingName)
+    );
+    let file = crate::parse("LIBRARY test\nEXPORTS\n    f NONAME\n").unwrap();
+    assert_eq!(
+        file.to_import_library(Machine::Arm64),
+        Err(ImportLibraryError::NonameWithoutOrdinal("f".into()))
+    );
+
+    // The 3 import descriptor objects and one member for each export are indexed with a `u16`
+    let mut file = crate::parse("LIBRARY test\n").unwrap();
+    file.exports = (0..u16::MAX - 3)
+        .map(|i| Export::new(alloc::format!("f{i}"), None, None, false, false, false))
+        .collect();
+    let library = file.to_import_library(Machine::X64).unwrap();
+    let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+    assert_eq!(read.exports.len(), usize::from(u16::MAX - 3));
+    file.exports
+        .push(Export::new("g".into(), None, None, false, false, false));
+    assert_eq!(
+        file.to_import_library(Machine::X64),
+        Err(ImportLibraryError::TooManyMembers(0x10000))
+    );
+}
+
+/// Generated with `llvm-dlltool -m i386 -d test.def -l llvm_dlltool_x86.lib`
+/// and `-m i386:x86-64` from LLVM 14.
+#[cfg(feature = "alloc")]
+#[test]
+fn import_library_matches_llvm() {
+    use crate::{Dialect, Machine};
+
+    const FILE: &str = "\
+LIBRARY test
+EXPORTS
+    func @5
+    var @6 DATA
+    hidden @7 NONAME
+    alias=func @8
+    internal PRIVATE
+    stdcall@8
+    ?method@@YAXXZ
+";
+
+    /// Symbols of the first linker member in member order.
+    fn first_linker_member(members: &[(&str, &[u8])]) -> alloc::vec::Vec<alloc::vec::Vec<u8>> {
+        let (name, data) = members[0];
+        assert_eq!(name, "/");
+        let count = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        data[4 + count * 4..]
+            .split(|&a| a == 0)
+            .take(count)
+            .map(<[u8]>::to_vec)
+            .collect()
+    }
+
+    // `llvm-dlltool` reads `stdcall@8` as a decorated name on x86
+    let (gnu, _) =
+        crate::parse_with_options(FILE, ParseOptions::new().dialect(Dialect::Gnu)).unwrap();
+    let msvc = crate::parse(FILE).unwrap();
+
+    for (file, machine, expected) in [
+        (
+            &gnu,
+            Machine::X86,
+            include_bytes!("../tests/llvm_dlltool_x86.lib").as_slice(),
+        ),
+        (
+            &msvc,
+            Machine::X64,
+            include_bytes!("../tests/llvm_dlltool_x64.lib").as_slice(),
+        ),
+    ] {
+        let library = file.to_import_library(machine).unwrap();
+        let members = archive_members(&library);
+        let expected = archive_members(expected);
+
+        // `llvm-dlltool` writes GNU archives without the second linker member and long names member
+        assert_eq!(members[1].0, "/");
+        assert_eq!(members[2].0, "//");
+        assert_eq!(expected[1].0, "test.dll/");
+        assert_eq!(members[3..], expected[1..]);
+
+        assert_eq!(
+            first_linker_member(&members),
+            first_linker_member(&expected)
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn from_import_library() {
+    use crate::{Export, ImportLibraryError, Machine};
+
+    let mut file = crate::parse(
+        "\
+LIBRARY test
+EXPORTS
+    func @5
+    var @6 DATA
+    hidden @7 NONAME
+    stdcall@8
+    ?method@@YAXXZ
+    exported EXPORTAS real
+",
+    )
+    .unwrap();
+    file.exports.push(
+        Export::new("undecorated".into(), None, None, false, false, false)
+            .with_argument_bytes(Some(4))
+            .with_import_alias(Some("undecorated".into())),
+    );
+    file.exports.push(
+        Export::new("args".into(), None, None, false, false, false).with_argument_bytes(Some(12)),
+    );
+
+    for machine in [Machine::X86, Machine::X64, Machine::Arm64, Machine::Arm64Ec] {
+        let library = file.to_import_library(machine).unwrap();
+        let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+
+        assert_eq!(read.name.as_deref(), Some("test"));
+        assert_eq!(read.is_library, Some(true));
+        assert_eq!(read.exports.len(), 8);
+        assert_eq!(read.to_import_library(machine).unwrap(), library);
+    }
+
+    let library = file.to_import_library(Machine::X86).unwrap();
+    let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+    assert_eq!(
+        read.exports[0],
+        Export::new("func".into(), None, Some(5), false, false, false)
+    );
+    assert_eq!(
+        read.exports[2],
+        Export::new("hidden".into(), None, Some(7), true, false, false)
+    );
+    assert_eq!(read.exports[3].name, "stdcall@8");
+    assert_eq!(read.exports[7].name, "args");
+    assert_eq!(read.exports[7].argument_bytes, Some(12));
+    assert_eq!(read.exports[6].import_alias.as_deref(), Some("undecorated"));
+    assert_eq!(read.exports[5].export_as.as_deref(), Some("real"));
+
+    let file = crate::parse("NAME app\nEXPORTS\n    func\n").unwrap();
+    let library = file.to_import_library(Machine::X64).unwrap();
+    let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+    assert_eq!(read.name.as_deref(), Some("app"));
+    assert_eq!(read.is_library, Some(false));
+
+    // Long import object with a hint/name entry
+    let mut object = alloc::vec::Vec::new();
+    object.extend_from_slice(&[
+        0x64, 0x86, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ]);
+    for (name, size, offset) in [(b".idata$5", 8_u32, 100_u32), (b".idata$6", 8, 108)] {
+        object.extend_from_slice(name);
+        object.extend_from_slice(&[0; 8]);
+        object.extend_from_slice(&size.to_le_bytes());
+        object.extend_from_slice(&offset.to_le_bytes());
+        object.extend_from_slice(&[0; 12]);
+        object.extend_from_slice(&0xC000_0040_u32.to_le_bytes());
+    }
+    object.extend_from_slice(&[0; 8]);
+    object.extend_from_slice(b"\x03\0func\0\0");
+    let mut library = alloc::vec::Vec::from(*b"!<arch>\n");
+    library.extend_from_slice(b"d000001.o/      0           0     0     644     116       `\n");
+    library.extend_from_slice(&object);
+    let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+    assert_eq!(read.name, None);
+    assert_eq!(
+        read.exports,
+        [Export::new(
+            "func".into(),
+            None,
+            Some(3),
+            false,
+            false,
+            true
+        )]
+    );
+
+    // Long import object by ordinal with a symbol count far larger than the object
+    for (symbol, expected) in [(b"__imp_fn", 1), (b"other_fn", 0)] {
+        let mut object = alloc::vec::Vec::new();
+        object.extend_from_slice(&[0x64, 0x86, 1, 0, 0, 0, 0, 0, 68, 0, 0, 0]);
+        object.extend_from_slice(&u32::MAX.to_le_bytes());
+        object.extend_from_slice(&[0; 4]);
+        object.extend_from_slice(b".idata$5");
+        object.extend_from_slice(&[0; 8]);
+        object.extend_from_slice(&8_u32.to_le_bytes());
+        object.extend_from_slice(&60_u32.to_le_bytes());
+        object.extend_from_slice(&[0; 12]);
+        object.extend_from_slice(&0xC000_0040_u32.to_le_bytes());
+        object.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0x80]);
+        object.extend_from_slice(symbol);
+        object.extend_from_slice(&[0; 10]);
+        let mut library = alloc::vec::Vec::from(*b"!<arch>\n");
+        library.extend_from_slice(b"d000001.o/      0           0     0     644     86        `\n");
+        library.extend_from_slice(&object);
+        let read = ModuleDefinitionFile::from_import_library(&library).unwrap();
+        assert_eq!(read.exports.len(), expected);
+    }
+
+    assert_eq!(
+        ModuleDefinitionFile::from_import_library(b"!<arch>\nshort"),
+        Err(ImportLibraryError::Truncated(68))
+    );
+    assert_eq!(
+        ModuleDefinitionFile::from_import_library(b"not an archive"),
+        Err(ImportLibraryError::InvalidArchive)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn version_script() {
+    use crate::{ElfError, Export};
+
+    let file = crate::parse(
+        "\
+LIBRARY my-lib.so
+VERSION 3
+EXPORTS
+    func
+    alias=func
+    ?method@@YAXXZ
+    forward=other.func
+    hidden @1 NONAME
+    internal PRIVATE
+",
+    )
+    .unwrap();
+
+    assert_eq!(
+        file.to_version_script().unwrap(),
+        "\
+MY_LIB_SO_3.0 {
+    global:
+        func;
+        alias;
+        \"?method@@YAXXZ\";
+        internal;
+    local:
+        *;
+};
+"
+    );
+    assert_eq!(
+        file.to_dynamic_list().unwrap(),
+        "{\n    func;\n    alias;\n    \"?method@@YAXXZ\";\n    internal;\n};\n"
+    );
+
+    assert_eq!(
+        ModuleDefinitionFile::default().to_version_script().unwrap(),
+        "{\n    local:\n        *;\n};\n"
+    );
+    let file = crate::parse("VERSION 1.2\n").unwrap();
+    assert!(file
+        .to_version_script()
+        .unwrap()
+        .starts_with("VERS_1.2 {\n"));
+
+    // Quoted names are read verbatim
+    let mut file = ModuleDefinitionFile::default();
+    file.exports
+        .push(Export::new("a\\b".into(), None, None, false, false, false));
+    assert_eq!(file.to_dynamic_list().unwrap(), "{\n    \"a\\b\";\n};\n");
+
+    file.exports
+        .push(Export::new("a\"b".into(), None, None, false, false, false));
+    assert_eq!(
+        file.to_version_script(),
+        Err(ElfError::InvalidName("a\"b".into()))
+    );
+    assert_eq!(
+        file.to_dynamic_list(),
+        Err(ElfError::InvalidName("a\"b".into()))
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn exported_symbols_list() {
+    use crate::{MachOOptions, MachOWarning};
+
+    let file = crate::parse(
+        "\
+LIBRARY foo
+EXPORTS
+    func
+    alias=func
+    var DATA
+    forward=other.func
+    hidden @1 NONAME
+    internal PRIVATE
+SECTIONS
+    .shared READ WRITE SHARED
+",
+    )
+    .unwrap();
+
+    let list = file.to_exported_symbols_list(MachOOptions::new());
+    assert_eq!(list.list, "_func\n_alias\n_var\n_hidden\n_internal\n");
+    assert!(list.alias_arguments.is_empty());
+    assert_eq!(
+        list.warnings,
+        [
+            MachOWarning::Forwarded("forward"),
+            MachOWarning::Ordinal {
+                name: "hidden",
+                ordinal: 1
+            },
+            MachOWarning::Noname("hidden"),
+            MachOWarning::Private("internal"),
+            MachOWarning::Section(".shared"),
+        ]
+    );
+
+    let list = file.to_exported_symbols_list(MachOOptions::new().alias_internal_names(true));
+    assert_eq!(list.alias_arguments, ["-alias", "_func", "_alias"]);
+}
+
+fn alloc_free_concat<'a>(a: &str, b: &str, buf: &'a mut [u8]) -> &'a str {
+    buf[..a.len()].copy_from_slice(a.as_bytes());
+    buf[a.len()..a.len() + b.len()].copy_from_slice(b.as_bytes());
+    core::str::from_utf8(&buf[..a.len() + b.len()]).unwrap()
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn syntax_tree_round_trip() {
+    use alloc::string::ToString;
+
+    for &file in TEST_FILES {
+        let tree = SyntaxTree::new(file);
+        assert_eq!(tree.to_string(), file);
+
+        let mut offset = 0;
+        for token in tree.tokens() {
+            let span = token.span.clone().unwrap();
+            assert_eq!(span.start, offset);
+            assert_eq!(&file[span.clone()], token.text);
+            offset = span.end;
+        }
+        assert_eq!(offset, file.len());
+
+        let parsed = ModuleDefinitionFile::new(file).unwrap();
+        assert_eq!(tree.exports().len(), parsed.exports.len());
+    }
+
+    let file =
+        "LIBRARY \"with \"\"quotes\"\"\" ; comment\r\n\r\nEXPORTS\r\n\tname=internal @1 NONAME";
+    let tree = SyntaxTree::new(file);
+    assert_eq!(tree.to_string(), file);
+    let kinds: alloc::vec::Vec<_> = tree.tokens().iter().map(|a| a.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            SyntaxKind::Word,
+            SyntaxKind::Whitespace,
+            SyntaxKind::QuotedString,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Comment,
+            SyntaxKind::Newline,
+            SyntaxKind::Newline,
+            SyntaxKind::Word,
+            SyntaxKind::Newline,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Word,
+            SyntaxKind::Equals,
+            SyntaxKind::Word,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Word,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Word,
+        ]
+    );
+
+    for file in ["\u{e9}", "LIBRARY \u{e9}\nEXPORTS\n    f\u{e9}\u{3000}"] {
+        assert_eq!(SyntaxTree::new(file).to_string(), file);
+    }
+    let tree = SyntaxTree::new("EXPORTS\n    caf\u{e9}");
+    assert_eq!(tree.tokens()[tree.exports()[0].name].text, "caf\u{e9}");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn syntax_tree_edit() {
+    use alloc::string::ToString;
+
+    let file = include_str!("../tests/zlib.def");
+    let mut tree = SyntaxTree::new(file);
+
+    let exports = tree.exports();
+    assert_eq!(tree.tokens()[exports[1].name].text, "deflate");
+
+    tree.set_export_ordinal(1, Some(3));
+    tree.set_export_internal_name(1, Some("deflate_impl"));
+    tree.set_export_data(1, true);
+    let edited = tree.to_string();
+    assert_eq!(
+        edited,
+        file.replacen("    deflate\n", "    deflate=deflate_impl @3 DATA\n", 1)
+    );
+
+    tree.set_export_ordinal(1, None);
+    tree.set_export_internal_name(1, None::<&str>);
+    tree.set_export_data(1, false);
+    assert_eq!(tree.to_string(), file);
+
+    tree.remove_export(0);
+    assert_eq!(tree.to_string(), file.replacen("    zlibVersion\n", "", 1));
+
+    tree.insert_export(0, "zlibVersion @1");
+    assert_eq!(
+        tree.to_string(),
+        file.replacen("    zlibVersion\n", "    zlibVersion @1\n", 1)
+    );
+
+    let mut tree = SyntaxTree::new(include_str!("../tests/dagor.def"));
+    tree.set_export_name(0, "Renamed");
+    tree.set_export_private(1, false);
+    assert_eq!(
+        tree.to_string(),
+        "\
+EXPORTS
+\tRenamed\t\t@1 PRIVATE
+\tLibNumberClasses\t@2
+\tLibClassDesc\t\t@3 PRIVATE
+\tLibVersion\t\t\t@4 PRIVATE
+SECTIONS
+\t.data READ WRITE
+"
+    );
+
+    // Names that would not be read as a single name are quoted
+    let mut tree = SyntaxTree::new("EXPORTS\n    a @1\n    b=c\n");
+    tree.set_export_name(0, "with space");
+    tree.set_export_internal_name(0, Some("say \"hi\""));
+    tree.set_export_internal_name(1, Some("DATA"));
+    let edited = tree.to_string();
+    assert_eq!(
+        edited,
+        "EXPORTS\n    \"with space\"=\"say \"\"hi\"\"\" @1\n    b=\"DATA\"\n"
+    );
+    assert_eq!(
+        tree.tokens()[tree.exports()[0].name].kind,
+        SyntaxKind::QuotedString
+    );
+    assert_eq!(tree.exports().len(), 2);
+    let exports = crate::parse(&edited).unwrap().exports;
+    assert_eq!(exports[0].name, "with space");
+    assert_eq!(exports[0].internal_name.as_deref(), Some("say \"hi\""));
+    assert_eq!(exports[0].ordinal, Some(1));
+    assert_eq!(exports[1].internal_name.as_deref(), Some("DATA"));
+    assert!(!exports[1].data);
+
+    let mut tree = SyntaxTree::new("EXPORTS a @1 b @2 ; comment");
+    tree.remove_export(0);
+    assert_eq!(tree.to_string(), "EXPORTS b @2 ; comment");
+    tree.remove_export(0);
+    assert_eq!(tree.to_string(), "EXPORTS ; comment");
+
+    let mut tree = SyntaxTree::new("LIBRARY lib\r\nEXPORTS\r\n  a");
+    tree.insert_export(1, "b");
+    assert_eq!(tree.to_string(), "LIBRARY lib\r\nEXPORTS\r\n  a\r\n  b");
+
+    let mut tree = SyntaxTree::new("LIBRARY lib\n");
+    tree.insert_export(0, "a");
+    assert_eq!(tree.to_string(), "LIBRARY lib\nEXPORTS\n    a\n");
+}
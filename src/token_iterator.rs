@@ -1,10 +1,18 @@
-use crate::parse_ref::RESERVED_WORDS;
+use crate::options::{Dialect, ParseOptions};
+use crate::parse_ref::{RESERVED_WORDS, SECTION_ATTRIBUTES};
+
+pub(crate) const BYTE_ORDER_MARK: char = '\u{feff}';
+
+/// Keywords that are not reserved words, but still have a meaning inside of a statement.
+pub(crate) const CONTEXTUAL_KEYWORDS: &[&str] = &["CLASS", "CONSTANT", "EXPORTAS"];
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub(crate) struct TokenIterator<'a> {
     pub rest: &'a str,
     pub offset: usize,
     pub version_token_encountered_tokens_ago: u8,
+    pub case_insensitive: bool,
+    pub dialect: Dialect,
 }
 
 fn trim_start(s: &str) -> (usize, &str) {
@@ -16,20 +24,65 @@ fn trim_start(s: &str) -> (usize, &str) {
 }
 
 fn trim_end(s: &str) -> (usize, &str) {
-    if let Some(i) = s.rfind(|a: char| !a.is_whitespace()) {
-        (0, &s[..=i])
+    if let Some((i, c)) = s.char_indices().rfind(|(_, a)| !a.is_whitespace()) {
+        (0, &s[..i + c.len_utf8()])
     } else {
         (s.len(), &s[..=s.len()])
     }
 }
 
+/// Keywords are returned in upper case when keywords are case-insensitive,
+/// everything else is returned unchanged.
+///
+/// Quoted strings keep the starting quote, so they are never keywords.
+pub(crate) fn keyword(token: &str, case_insensitive: bool) -> &str {
+    if !case_insensitive {
+        return token;
+    }
+
+    RESERVED_WORDS
+        .iter()
+        .chain(SECTION_ATTRIBUTES)
+        .chain(CONTEXTUAL_KEYWORDS)
+        .find(|k| k.eq_ignore_ascii_case(token))
+        .copied()
+        .unwrap_or(token)
+}
+
+/// Returns [`true`] if `s` is any keyword, including attributes.
+pub(crate) fn is_keyword(s: &str) -> bool {
+    RESERVED_WORDS.contains(&s)
+        || SECTION_ATTRIBUTES.contains(&s)
+        || CONTEXTUAL_KEYWORDS.contains(&s)
+}
+
+/// Embedded quotes are written as two double quotes (`""`).
+pub(crate) fn find_closing_quote(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '"' && chars.next_if(|&(_, c)| c == '"').is_none() {
+            return Some(i);
+        }
+    }
+
+    None
+}
+
 impl<'a> TokenIterator<'a> {
-    pub fn new(s: &'a str) -> Self {
+    pub fn new(s: &'a str, options: ParseOptions) -> Self {
+        let (bom, s) = match s.strip_prefix(BYTE_ORDER_MARK) {
+            Some(s) => (BYTE_ORDER_MARK.len_utf8(), s),
+            None => (0, s),
+        };
+
         let (offset, rest) = trim_start(s);
         Self {
             rest,
-            offset,
+            offset: bom + offset,
             version_token_encountered_tokens_ago: 0,
+            case_insensitive: options.is_case_insensitive(),
+            dialect: options.get_dialect(),
         }
     }
 
@@ -41,21 +94,14 @@ impl<'a> TokenIterator<'a> {
         }
     }
 
+    /// Skips every comment at the start of `rest`,
+    /// returns [`false`] if the last comment continues until the end of the file.
     pub fn remove_comment(&mut self) -> bool {
-        if self.rest.starts_with(crate::parse_ref::COMMENT) {
-            if let Some(i) = self.rest.find(|a| a == '\r') {
-                if &self.rest[i..i + 1] == "\r\n" {
-                    self.set_rest(i + 1, false);
-                    return true;
-                }
-            }
-
-            return if let Some(i) = self.rest.find(|a| a == '\n') {
-                self.set_rest(i, false);
-                true
-            } else {
-                false
+        while self.rest.starts_with(crate::parse_ref::COMMENT) {
+            let Some(i) = self.rest.find('\n') else {
+                return false;
             };
+            self.set_rest(i, false);
         }
 
         true
@@ -90,20 +136,19 @@ impl<'a> TokenIterator<'a> {
             return Some(tmp);
         }
 
-        if let Some(i) = self.rest[1..].find(|a: char| {
-            (!find_matching_quote && a.is_whitespace())
-                || (find_matching_quote && a == '"')
-                || (!find_matching_quote
-                    && (a == ','
-                        || a == ':'
-                        || a == '='
-                        || (self.version_token_encountered_tokens_ago != 0 && a == '.')))
-        }) {
-            let offset = if find_matching_quote { 2 } else { 1 };
+        let first = self.rest.chars().next().map_or(1, char::len_utf8);
+        let end = if find_matching_quote {
+            find_closing_quote(&self.rest[first..])
+        } else {
+            self.find_token_end(&self.rest[first..])
+        };
+
+        if let Some(i) = end {
+            let closing_quote = if find_matching_quote { 1 } else { 0 };
 
             // Deliberately leave in the starting quote in order to discern from real keywords
-            let tmp = &self.rest[..i + 1];
-            self.set_rest(i + offset, peek);
+            let tmp = &self.rest[..i + first];
+            self.set_rest(i + first + closing_quote, peek);
 
             if !self.remove_comment() {
                 return None;
@@ -112,15 +157,37 @@ impl<'a> TokenIterator<'a> {
             return Some(tmp);
         }
 
-        // Don't need to update self.offset since empty strings should just return immediately
+        // Point to directly after the last token so that errors about missing arguments are placed correctly
         let (_, tmp) = trim_end(self.rest);
         if !peek {
             self.rest = "";
+            self.offset += tmp.len();
         }
 
         Some(tmp)
     }
 
+    /// Quoted parts inside of a token, as in `module."entry name"`, are part of the token.
+    fn find_token_end(&self, s: &str) -> Option<usize> {
+        let mut start = 0;
+        loop {
+            let i = start
+                + s[start..].find(|a: char| {
+                    a.is_whitespace()
+                        || a == ','
+                        || a == ':'
+                        || a == '='
+                        || a == '"'
+                        || (self.version_token_encountered_tokens_ago != 0 && a == '.')
+                })?;
+            if !s[i..].starts_with('"') {
+                return Some(i);
+            }
+
+            start = i + 1 + find_closing_quote(&s[i + 1..])? + 1;
+        }
+    }
+
     fn eat_token_state_wrapper(&mut self, peek: bool) -> Option<&'a str> {
         let token = self.eat_token_inner(peek);
         if self.version_token_encountered_tokens_ago == 1 {
@@ -130,7 +197,7 @@ impl<'a> TokenIterator<'a> {
         }
 
         if let Some(token) = token {
-            if token == "VERSION" {
+            if self.keyword(token) == "VERSION" {
                 self.version_token_encountered_tokens_ago = 1;
             }
         }
@@ -142,6 +209,16 @@ impl<'a> TokenIterator<'a> {
         self.eat_token_state_wrapper(false)
     }
 
+    /// Same as [`eat_token`](Self::eat_token), but also returns the offset of the token.
+    pub fn eat_token_spanned(&mut self) -> Option<(usize, &'a str)> {
+        if !self.remove_comment() {
+            return None;
+        }
+
+        let offset = self.offset;
+        self.eat_token().map(|token| (offset, token))
+    }
+
     pub fn peek_token(&mut self) -> Option<&'a str> {
         self.eat_token_inner(true)
     }
@@ -150,7 +227,7 @@ impl<'a> TokenIterator<'a> {
         let t = self.peek_token();
         match t {
             None => false,
-            Some(s) => s == token,
+            Some(s) => self.keyword(s) == token,
         }
     }
 
@@ -158,7 +235,11 @@ impl<'a> TokenIterator<'a> {
         let t = self.peek_token();
         match t {
             None => false,
-            Some(s) => RESERVED_WORDS.contains(&s),
+            Some(s) => RESERVED_WORDS.contains(&self.keyword(s)),
         }
     }
+
+    pub fn keyword(&self, token: &'a str) -> &'a str {
+        keyword(token, self.case_insensitive)
+    }
 }
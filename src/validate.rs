@@ -0,0 +1,225 @@
+use crate::error::{Severity, Span};
+use crate::parse_ref::{ExportRef, ModuleDefinitionFileRef, SectionRef};
+use core::fmt::{Display, Formatter};
+
+/// Highest ordinal that fits in the export address table.
+pub(crate) const MAX_ORDINAL: u64 = u16::MAX as u64;
+
+/// Problem found by [`ModuleDefinitionFileRef::validate`] or
+/// [`ModuleDefinitionFile::validate`](crate::ModuleDefinitionFile::validate)
+/// that would make the file fail to link or behave unexpectedly.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Lint<'a> {
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// Kind of problem.
+    pub kind: LintKind<'a>,
+    /// Span of the name of the offending item.
+    ///
+    /// Is [`None`] for files that were not parsed from a string.
+    pub span: Option<Span>,
+}
+
+impl<'a> Lint<'a> {
+    /// Create new [`Lint`] with the default severity of `kind`.
+    pub const fn new(kind: LintKind<'a>, span: Option<Span>) -> Self {
+        Self {
+            severity: kind.severity(),
+            kind,
+            span,
+        }
+    }
+
+    /// Render the lint along with the offending line of `source` and a caret underline.
+    ///
+    /// `source` must be the same string that was parsed.
+    /// Lints without a [`span`](Self::span) are rendered as a single line.
+    #[cfg(feature = "alloc")]
+    pub fn render(&self, source: &str) -> alloc::string::String {
+        match self.span {
+            Some(span) => crate::error::render(source, span, self.severity, self),
+            None => alloc::format!("{}: {}\n", self.severity, self),
+        }
+    }
+}
+
+impl Display for Lint<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            LintKind::DuplicateExportName(a) => {
+                write!(f, "export '{a}' is specified more than once")
+            }
+            LintKind::DuplicateOrdinal(a) => {
+                write!(f, "ordinal '{a}' is used by more than one export")
+            }
+            LintKind::NonameWithoutOrdinal(a) => {
+                write!(f, "export '{a}' is 'NONAME' without an ordinal")
+            }
+            LintKind::OrdinalOutOfRange(a) => {
+                write!(f, "ordinal '{a}' is not between 1 and {MAX_ORDINAL}")
+            }
+            LintKind::PrivateData(a) => {
+                write!(
+                    f,
+                    "export '{a}' is both 'PRIVATE' and 'DATA', 'DATA' only affects the import library"
+                )
+            }
+            LintKind::DuplicateSection(a) => {
+                write!(f, "section '{a}' is specified more than once")
+            }
+        }
+    }
+}
+
+/// Kind of lint.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum LintKind<'a> {
+    /// Export name appears more than once in `EXPORTS`.
+    DuplicateExportName(&'a str),
+    /// Ordinal is assigned to more than one export.
+    DuplicateOrdinal(u64),
+    /// `NONAME` without an ordinal, which exports nothing by name or ordinal.
+    NonameWithoutOrdinal(&'a str),
+    /// Ordinal is 0 or does not fit in 16 bits.
+    OrdinalOutOfRange(u64),
+    /// `PRIVATE` together with `DATA`.
+    ///
+    /// `DATA` only changes the import library, which `PRIVATE` excludes the export from.
+    PrivateData(&'a str),
+    /// Section name appears more than once in `SECTIONS`.
+    DuplicateSection(&'a str),
+}
+
+impl LintKind<'_> {
+    /// Default severity of the lint.
+    ///
+    /// Problems that `link.exe` rejects are errors, everything else is a warning.
+    pub const fn severity(&self) -> Severity {
+        match self {
+            LintKind::DuplicateExportName(_)
+            | LintKind::DuplicateOrdinal(_)
+            | LintKind::OrdinalOutOfRange(_) => Severity::Error,
+            LintKind::NonameWithoutOrdinal(_)
+            | LintKind::PrivateData(_)
+            | LintKind::DuplicateSection(_) => Severity::Warning,
+        }
+    }
+}
+
+impl<'a> ModuleDefinitionFileRef<'a> {
+    /// Check the [`Exports`](crate::Exports) and [`Sections`](crate::Sections) for mistakes
+    /// that would make the file fail to link or behave unexpectedly.
+    ///
+    /// Every [`Lint`] is passed to `lints`, in order of appearance.
+    /// Items that can not be parsed are ignored, use [`errors`](Self::errors) for those.
+    ///
+    /// Without the `alloc` feature the items are parsed again for every item in order to find duplicates,
+    /// which takes quadratic time.
+    ///
+    /// ```rust
+    /// # use msvc_def::{LintKind, Lint, Span};
+    /// let file = msvc_def::parse_ref("EXPORTS\n    a @1\n    b @1\n").unwrap();
+    ///
+    /// let mut lint = None;
+    /// file.validate(|l| lint = Some(l));
+    /// assert_eq!(lint, Some(Lint::new(LintKind::DuplicateOrdinal(1), Some(Span::new(21, 22)))));
+    /// ```
+    pub fn validate(&self, mut lints: impl FnMut(Lint<'a>)) {
+        let mut exports = self.exports;
+        let exports = core::iter::from_fn(move || loop {
+            match exports.next_spanned()? {
+                (span, Ok(e)) => return Some((Some(span), e)),
+                (_, Err(_)) => continue,
+            }
+        });
+
+        let mut sections = self.sections;
+        let sections = core::iter::from_fn(move || loop {
+            match sections.next_spanned()? {
+                (span, Ok(s)) => return Some((Some(span), s)),
+                (_, Err(_)) => continue,
+            }
+        });
+
+        validate_inner(exports, sections, &mut lints);
+    }
+}
+
+/// Duplicates are found by collecting the names and ordinals once.
+#[cfg(feature = "alloc")]
+pub(crate) fn validate_inner<'a>(
+    exports: impl Iterator<Item = (Option<Span>, ExportRef<'a>)>,
+    sections: impl Iterator<Item = (Option<Span>, SectionRef<'a>)>,
+    lints: &mut impl FnMut(Lint<'a>),
+) {
+    use alloc::collections::BTreeSet;
+
+    let mut names = BTreeSet::new();
+    let mut ordinals = BTreeSet::new();
+    for (span, e) in exports {
+        validate_export(&e, span, lints);
+
+        if !names.insert(e.name) {
+            lints(Lint::new(LintKind::DuplicateExportName(e.name), span));
+        }
+
+        if let Some(ordinal) = e.ordinal {
+            if !ordinals.insert(ordinal) {
+                lints(Lint::new(LintKind::DuplicateOrdinal(ordinal), span));
+            }
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    for (span, s) in sections {
+        if !names.insert(s.name) {
+            lints(Lint::new(LintKind::DuplicateSection(s.name), span));
+        }
+    }
+}
+
+/// Items are iterated once per item in order to find duplicates without allocating,
+/// which parses the file again for every item.
+#[cfg(not(feature = "alloc"))]
+pub(crate) fn validate_inner<'a>(
+    exports: impl Iterator<Item = (Option<Span>, ExportRef<'a>)> + Clone,
+    sections: impl Iterator<Item = (Option<Span>, SectionRef<'a>)> + Clone,
+    lints: &mut impl FnMut(Lint<'a>),
+) {
+    for (i, (span, e)) in exports.clone().enumerate() {
+        let mut previous = exports.clone().take(i).map(|(_, e)| e);
+
+        validate_export(&e, span, lints);
+
+        if previous.clone().any(|a| a.name == e.name) {
+            lints(Lint::new(LintKind::DuplicateExportName(e.name), span));
+        }
+
+        if let Some(ordinal) = e.ordinal {
+            if previous.any(|a| a.ordinal == Some(ordinal)) {
+                lints(Lint::new(LintKind::DuplicateOrdinal(ordinal), span));
+            }
+        }
+    }
+
+    for (i, (span, s)) in sections.clone().enumerate() {
+        if sections.clone().take(i).any(|(_, a)| a.name == s.name) {
+            lints(Lint::new(LintKind::DuplicateSection(s.name), span));
+        }
+    }
+}
+
+/// Lints that only depend on the export itself.
+fn validate_export<'a>(e: &ExportRef<'a>, span: Option<Span>, lints: &mut impl FnMut(Lint<'a>)) {
+    if let Some(ordinal) = e.ordinal {
+        if ordinal == 0 || ordinal > MAX_ORDINAL {
+            lints(Lint::new(LintKind::OrdinalOutOfRange(ordinal), span));
+        }
+    } else if e.noname {
+        lints(Lint::new(LintKind::NonameWithoutOrdinal(e.name), span));
+    }
+
+    if e.private && e.data {
+        lints(Lint::new(LintKind::PrivateData(e.name), span));
+    }
+}
@@ -0,0 +1,519 @@
+use crate::error::{ParseError, WriteError};
+use crate::options::{Dialect, ExportOrder, Indent, KeywordCase, Radix, WriteOptions};
+use crate::parse_ref::{needs_quotes, DOUBLE_QUOTE};
+use crate::{ExportRef, ImportRef, SectionRef};
+use core::fmt::Write;
+
+/// Tab width assumed when aligning ordinals with [`Indent::Tab`].
+const TAB_WIDTH: usize = 4;
+
+/// Statements that are not made up of items.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Header<'a> {
+    pub name: Option<&'a str>,
+    pub is_library: Option<bool>,
+    pub base_address: Option<u64>,
+    pub heap_reserve: Option<u64>,
+    pub heap_commit: Option<u64>,
+    pub stack_reserve: Option<u64>,
+    pub stack_commit: Option<u64>,
+    pub stub: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub major_version: Option<u16>,
+    pub minor_version: Option<u16>,
+}
+
+/// Shared by the writers of [`ModuleDefinitionFileRef`](crate::ModuleDefinitionFileRef)
+/// and [`ModuleDefinitionFile`](crate::ModuleDefinitionFile).
+///
+/// Invalid items are skipped unless `strict` is set.
+///
+/// With `escape` embedded quotes in names are doubled when written,
+/// otherwise they must already be written as two double quotes (`""`) like in the parsed file.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_file<'a>(
+    buf: &mut impl Write,
+    header: &Header<'_>,
+    sections: impl Iterator<Item = Result<SectionRef<'a>, ParseError<'a>>>,
+    exports: impl Iterator<Item = Result<ExportRef<'a>, ParseError<'a>>> + Clone,
+    imports: impl Iterator<Item = Result<ImportRef<'a>, ParseError<'a>>>,
+    options: WriteOptions,
+    strict: bool,
+    escape: bool,
+) -> Result<(), WriteError<'a>> {
+    let mut w = Writer {
+        buf,
+        options,
+        escape,
+    };
+
+    w.header(header)?;
+
+    let mut has_header = false;
+    for section in sections {
+        let section = match section {
+            Ok(section) => section,
+            Err(e) if strict => return Err(e.into()),
+            Err(_) => continue,
+        };
+
+        if !has_header {
+            w.keyword("SECTIONS")?;
+            w.newline()?;
+            has_header = true;
+        }
+
+        w.section(&section)?;
+    }
+
+    if strict {
+        if let Some(Err(e)) = exports.clone().find(Result::is_err) {
+            return Err(e.into());
+        }
+    }
+    let exports = exports.filter_map(Result::ok);
+
+    let width = if options.get_align_ordinals() {
+        exports
+            .clone()
+            .filter(|e| e.ordinal.is_some())
+            .map(|e| w.name_width(&e))
+            .max()
+    } else {
+        None
+    };
+
+    if exports.clone().next().is_some() {
+        w.keyword("EXPORTS")?;
+        w.newline()?;
+    }
+
+    match options.get_export_order() {
+        ExportOrder::Source => {
+            for export in exports {
+                w.export(&export, width)?;
+            }
+        }
+        #[cfg(feature = "alloc")]
+        order => {
+            let mut sorted = exports.collect::<alloc::vec::Vec<_>>();
+            // Stable, so exports with the same key stay in source order
+            sorted.sort_by_key(|e| sort_key(order, e));
+
+            for export in &sorted {
+                w.export(export, width)?;
+            }
+        }
+        #[cfg(not(feature = "alloc"))]
+        order => {
+            // Selection instead of sorting in order to not require `alloc`
+            let mut previous = None;
+            while let Some((key, export)) = exports
+                .clone()
+                .enumerate()
+                .map(|(i, e)| ((sort_key(order, &e), i), e))
+                .filter(|(key, _)| previous.map_or(true, |p| *key > p))
+                .min_by_key(|(key, _)| *key)
+            {
+                w.export(&export, width)?;
+                previous = Some(key);
+            }
+        }
+    }
+
+    has_header = false;
+    for import in imports {
+        let import = match import {
+            Ok(import) => import,
+            Err(e) if strict => return Err(e.into()),
+            Err(_) => continue,
+        };
+
+        if !has_header {
+            w.keyword("IMPORTS")?;
+            w.newline()?;
+            has_header = true;
+        }
+
+        w.import(&import)?;
+    }
+
+    Ok(())
+}
+
+fn sort_key<'a>(order: ExportOrder, export: &ExportRef<'a>) -> (bool, u64, &'a str) {
+    match order {
+        ExportOrder::Source => (false, 0, ""),
+        ExportOrder::Name => (false, 0, export.name),
+        ExportOrder::Ordinal => (export.ordinal.is_none(), export.ordinal.unwrap_or(0), ""),
+    }
+}
+
+struct Writer<'w, W> {
+    buf: &'w mut W,
+    options: WriteOptions,
+    escape: bool,
+}
+
+impl<W: Write> Writer<'_, W> {
+    fn header(&mut self, header: &Header<'_>) -> core::fmt::Result {
+        if let Some(name) = header.name {
+            if let Some(is_lib) = header.is_library {
+                self.keyword(if is_lib { "LIBRARY" } else { "NAME" })?;
+                self.buf.write_str(" ")?;
+                write_name(self.buf, name, self.escape, false)?;
+            }
+
+            if let Some(base) = header.base_address {
+                self.buf.write_str(" ")?;
+                self.keyword("BASE")?;
+                self.buf.write_str("=")?;
+                self.number(base)?;
+            }
+
+            self.newline()?;
+        }
+
+        if let Some(reserve) = header.heap_reserve {
+            self.double_arg("HEAPSIZE", reserve, header.heap_commit)?;
+        }
+
+        if let Some(reserve) = header.stack_reserve {
+            self.double_arg("STACKSIZE", reserve, header.stack_commit)?;
+        }
+
+        if let Some(stub) = header.stub {
+            self.keyword("STUB")?;
+            self.buf.write_str(":")?;
+            write_name(self.buf, stub, self.escape, false)?;
+            self.newline()?;
+        }
+
+        if let Some(description) = header.description {
+            self.keyword("DESCRIPTION")?;
+            self.buf.write_str(" ")?;
+            write_name(self.buf, description, self.escape, true)?;
+            self.newline()?;
+        }
+
+        if let Some(major_version) = header.major_version {
+            self.keyword("VERSION")?;
+            write!(self.buf, " {major_version}")?;
+            if let Some(minor_version) = header.minor_version {
+                write!(self.buf, ".{minor_version}")?;
+            }
+            self.newline()?;
+        }
+
+        Ok(())
+    }
+
+    fn double_arg(
+        &mut self,
+        keyword: &str,
+        reserve: u64,
+        commit: Option<u64>,
+    ) -> core::fmt::Result {
+        self.keyword(keyword)?;
+        self.buf.write_str(" ")?;
+        self.number(reserve)?;
+        if let Some(commit) = commit {
+            self.buf.write_str(",")?;
+            self.number(commit)?;
+        }
+        self.newline()
+    }
+
+    fn section(&mut self, section: &SectionRef<'_>) -> core::fmt::Result {
+        self.indent()?;
+        write_name(self.buf, section.name, self.escape, false)?;
+
+        for (set, attribute) in [
+            (section.read, "READ"),
+            (section.write, "WRITE"),
+            (section.execute, "EXECUTE"),
+            (section.shared, "SHARED"),
+        ] {
+            if set {
+                self.buf.write_str(" ")?;
+                self.keyword(attribute)?;
+            }
+        }
+
+        self.newline()
+    }
+
+    fn export(&mut self, export: &ExportRef<'_>, width: Option<usize>) -> core::fmt::Result {
+        self.indent()?;
+        export_name(self.buf, export, self.equals(), self.escape)?;
+
+        if let Some(ordinal) = export.ordinal {
+            self.align(export, width)?;
+            write!(self.buf, "@{ordinal}")?;
+            if export.noname {
+                self.buf.write_str(" ")?;
+                self.keyword("NONAME")?;
+            }
+        }
+
+        if export.private {
+            self.buf.write_str(" ")?;
+            self.keyword("PRIVATE")?;
+        }
+
+        if export.data {
+            self.buf.write_str(" ")?;
+            self.keyword("DATA")?;
+        }
+
+        if let Some(export_as) = export.export_as {
+            self.buf.write_str(" ")?;
+            self.keyword("EXPORTAS")?;
+            self.buf.write_str(" ")?;
+            write_name(self.buf, export_as, self.escape, false)?;
+        }
+
+        if self.options.get_dialect() == Dialect::Gnu {
+            if export.constant {
+                self.buf.write_str(" ")?;
+                self.keyword("CONSTANT")?;
+            }
+
+            if let Some(import_alias) = export.import_alias {
+                self.buf.write_str(" == ")?;
+                write_name(self.buf, import_alias, self.escape, false)?;
+            }
+        }
+
+        self.newline()
+    }
+
+    fn name_width(&self, export: &ExportRef<'_>) -> usize {
+        let mut counter = Counter(0);
+        // Counting never fails
+        let _ = export_name(&mut counter, export, self.equals(), self.escape);
+        counter.0
+    }
+
+    /// Padding between the name and the ordinal.
+    fn align(&mut self, export: &ExportRef<'_>, width: Option<usize>) -> core::fmt::Result {
+        let Some(width) = width else {
+            return self.buf.write_str(" ");
+        };
+        let name_width = self.name_width(export);
+
+        match self.options.get_indent() {
+            Indent::Spaces(_) => {
+                for _ in name_width..=width {
+                    self.buf.write_str(" ")?;
+                }
+            }
+            Indent::Tab => {
+                let end = TAB_WIDTH + name_width;
+                let column = (TAB_WIDTH + width) / TAB_WIDTH * TAB_WIDTH + TAB_WIDTH;
+                for _ in end / TAB_WIDTH..column / TAB_WIDTH {
+                    self.buf.write_str("\t")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn import(&mut self, import: &ImportRef<'_>) -> core::fmt::Result {
+        self.indent()?;
+        if let Some(internal_name) = import.internal_name {
+            write_name(self.buf, internal_name, self.escape, false)?;
+            self.buf.write_str(self.equals())?;
+        }
+
+        write_name(self.buf, import.module, self.escape, false)?;
+        self.buf.write_str(".")?;
+        if let Some(entry_name) = import.entry_name {
+            // Entry names can not contain periods and would otherwise be read as an ordinal
+            let always =
+                entry_name.contains('.') || entry_name.starts_with(|a: char| a.is_ascii_digit());
+            write_name(self.buf, entry_name, self.escape, always)?;
+        } else if let Some(ordinal) = import.ordinal {
+            write!(self.buf, "@{ordinal}")?;
+        }
+
+        self.newline()
+    }
+
+    fn keyword(&mut self, keyword: &str) -> core::fmt::Result {
+        match self.options.get_keyword_case() {
+            KeywordCase::Upper => self.buf.write_str(keyword),
+            KeywordCase::Lower => {
+                for c in keyword.chars() {
+                    self.buf.write_char(c.to_ascii_lowercase())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn number(&mut self, n: u64) -> core::fmt::Result {
+        match self.options.get_radix() {
+            Radix::Hexadecimal => write!(self.buf, "{n:#X}"),
+            Radix::Decimal => write!(self.buf, "{n}"),
+        }
+    }
+
+    fn indent(&mut self) -> core::fmt::Result {
+        match self.options.get_indent() {
+            Indent::Spaces(n) => {
+                for _ in 0..n {
+                    self.buf.write_str(" ")?;
+                }
+                Ok(())
+            }
+            Indent::Tab => self.buf.write_str("\t"),
+        }
+    }
+
+    const fn equals(&self) -> &'static str {
+        if self.options.get_spaces_around_equals() {
+            " = "
+        } else {
+            "="
+        }
+    }
+
+    fn newline(&mut self) -> core::fmt::Result {
+        self.buf.write_str(self.options.get_line_ending().as_str())
+    }
+}
+
+/// Name and internal name of an export, which is the column before the ordinal.
+fn export_name(
+    buf: &mut impl Write,
+    export: &ExportRef<'_>,
+    equals: &str,
+    escape: bool,
+) -> core::fmt::Result {
+    let quote = if needs_quotes(export.name) {
+        DOUBLE_QUOTE
+    } else {
+        ""
+    };
+
+    buf.write_str(quote)?;
+    write_escaped(buf, export.name, escape)?;
+    // Inside of the quotes so that it is not read as an ordinal
+    if let Some(argument_bytes) = export.argument_bytes {
+        write!(buf, "@{argument_bytes}")?;
+    }
+    buf.write_str(quote)?;
+
+    if let Some(internal_name) = export.internal_name {
+        buf.write_str(equals)?;
+        write_name(buf, internal_name, escape, false)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `s` surrounded by quotes if it can not be read back without them, or if `always` is set.
+pub(crate) fn write_name(
+    buf: &mut impl Write,
+    s: &str,
+    escape: bool,
+    always: bool,
+) -> core::fmt::Result {
+    let quote = if always || needs_quotes(s) {
+        DOUBLE_QUOTE
+    } else {
+        ""
+    };
+
+    buf.write_str(quote)?;
+    write_escaped(buf, s, escape)?;
+    buf.write_str(quote)
+}
+
+/// Doubles embedded quotes if `escape` is set.
+fn write_escaped(buf: &mut impl Write, s: &str, escape: bool) -> core::fmt::Result {
+    if !escape {
+        return buf.write_str(s);
+    }
+
+    for (i, part) in s.split(DOUBLE_QUOTE).enumerate() {
+        if i != 0 {
+            buf.write_str("\"\"")?;
+        }
+        buf.write_str(part)?;
+    }
+
+    Ok(())
+}
+
+/// Counts the bytes that would be written.
+pub(crate) struct Length(pub usize);
+
+impl Write for Length {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Counts the characters that would be written.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.chars().count();
+        Ok(())
+    }
+}
+
+/// Adapter for writing to a [`std::io::Write`] with
+/// [`ModuleDefinitionFileRef::write_to`](crate::ModuleDefinitionFileRef::write_to) and
+/// [`ModuleDefinitionFile::write_to`](crate::ModuleDefinitionFile::write_to).
+///
+/// Since [`core::fmt::Write`] can not return the underlying error, it is kept until it is
+/// retrieved with [`take_error`](Self::take_error).
+///
+/// ```rust
+/// # use msvc_def::IoWriter;
+/// let file = msvc_def::parse_ref("LIBRARY mylib\n").unwrap();
+///
+/// let mut w = IoWriter::new(Vec::new());
+/// file.write_to(&mut w).unwrap();
+/// assert_eq!(w.into_inner(), b"LIBRARY mylib\n");
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoWriter<W> {
+    inner: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoWriter<W> {
+    /// Create new [`IoWriter`].
+    pub const fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// The error that made the last write fail, if any.
+    pub fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
+
+    /// Get back the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            core::fmt::Error
+        })
+    }
+}